@@ -1,79 +1,252 @@
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 use std::{env, fs};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut path = None;
+    let mut rules = BlinkRules::default();
+    let mut modulus = None;
+    let mut blinks = Vec::new();
 
-    if let Some(path) = args.get(1) {
-        let mut pebble_line = PebbleLine::from_str(fs::read_to_string(path)?.as_str())?;
+    let mut args = env::args().skip(1);
 
-        println!(
-            "Pebbles after 25 blinks: {}",
-            pebble_line.total_pebbles_after_blinks(25)
-        );
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rules" => rules = args.next().ok_or("Missing rules")?.parse()?,
+            "--modulus" => modulus = Some(args.next().ok_or("Missing modulus")?.parse()?),
+            "--blinks" => blinks.push(args.next().ok_or("Missing blinks")?.parse()?),
+            _ => path = Some(arg),
+        }
+    }
 
-        println!(
-            "Pebbles after 75 blinks: {}",
-            pebble_line.total_pebbles_after_blinks(75)
-        );
+    if blinks.is_empty() {
+        blinks = vec![25, 75];
+    }
+
+    if let Some(path) = path {
+        let mut pebble_line = PebbleLine::from_str(fs::read_to_string(path)?.as_str())?
+            .with_rules(rules)
+            .with_modulus(modulus)?;
+
+        println!("Rules: {}", pebble_line.rules);
+
+        for blinks in blinks {
+            let pebbles = pebble_line.total_pebbles_after_blinks(blinks)?;
+
+            if let Some(modulus) = modulus {
+                println!(
+                    "Pebbles after {} blinks (mod {}): {}",
+                    blinks, modulus, pebbles
+                );
+            } else {
+                println!("Pebbles after {} blinks: {}", blinks, pebbles);
+            }
+        }
 
         Ok(())
     } else {
-        Err("Usage: day11 INPUT_FILE_PATH".into())
+        Err("Usage: day11 [--rules RULES] [--modulus M] [--blinks N]... INPUT_FILE_PATH".into())
     }
 }
 
 #[derive(Clone)]
 struct PebbleLine {
     pebbles: Vec<u64>,
-    cache: HashMap<(u64, usize), u64>,
+    rules: BlinkRules,
+    modulus: Option<u128>,
+    transitions: HashMap<u64, Vec<u64>>,
 }
 
 impl PebbleLine {
-    pub fn total_pebbles_after_blinks(&mut self, blinks: usize) -> u64 {
-        self.pebbles
-            .clone()
-            .iter()
-            .map(|&pebble| self.pebbles_after_blinks(pebble, blinks))
-            .sum()
-    }
-
-    fn pebbles_after_blinks(&mut self, pebble: u64, blinks: usize) -> u64 {
-        if blinks == 0 {
-            1
-        } else if let Some(&pebbles) = self.cache.get(&(pebble, blinks)) {
-            pebbles
+    pub fn with_rules(self, rules: BlinkRules) -> Self {
+        PebbleLine {
+            rules,
+            transitions: HashMap::new(),
+            ..self
+        }
+    }
+
+    /// Counts pebbles modulo the given modulus instead of exactly, so counts never overflow.
+    pub fn with_modulus(self, modulus: Option<u128>) -> Result<Self, Box<dyn Error>> {
+        if modulus == Some(0) {
+            Err("Modulus must be positive".into())
         } else {
-            let pebbles = if pebble == 0 {
-                self.pebbles_after_blinks(1, blinks - 1)
-            } else if Self::has_even_decimal_digits(pebble) {
-                let (left, right) = Self::split_pebble(pebble);
+            Ok(PebbleLine { modulus, ..self })
+        }
+    }
+
+    pub fn total_pebbles_after_blinks(&mut self, blinks: usize) -> Result<u128, Box<dyn Error>> {
+        let mut counts = HashMap::new();
+
+        for &pebble in &self.pebbles {
+            *counts.entry(pebble).or_insert(0) += 1;
+        }
+
+        // Later additions assume counts are already reduced
+        if let Some(modulus) = self.modulus {
+            counts.values_mut().for_each(|count| *count %= modulus);
+        }
 
-                self.pebbles_after_blinks(left, blinks - 1)
-                    + self.pebbles_after_blinks(right, blinks - 1)
+        // Pebbles with the same number always change the same way, so we only need to track how
+        // many of each number there are
+        for _ in 0..blinks {
+            counts = self.blink(&counts)?;
+        }
+
+        counts
+            .values()
+            .try_fold(0, |total, &count| self.add_counts(total, count))
+    }
+
+    fn blink(&mut self, counts: &HashMap<u64, u128>) -> Result<HashMap<u64, u128>, Box<dyn Error>> {
+        let mut next_counts = HashMap::with_capacity(counts.len());
+
+        for (&pebble, &count) in counts {
+            if !self.transitions.contains_key(&pebble) {
+                self.transitions.insert(pebble, self.rules.apply(pebble)?);
+            }
+
+            for &next_pebble in &self.transitions[&pebble] {
+                let next_count = next_counts.entry(next_pebble).or_insert(0);
+                *next_count = self.add_counts(*next_count, count)?;
+            }
+        }
+
+        Ok(next_counts)
+    }
+
+    fn add_counts(&self, a: u128, b: u128) -> Result<u128, Box<dyn Error>> {
+        if let Some(modulus) = self.modulus {
+            // Both counts are already reduced, so only the carry out of the top bit needs care
+            let (sum, overflowed) = a.overflowing_add(b);
+
+            if overflowed || sum >= modulus {
+                Ok(sum.wrapping_sub(modulus))
             } else {
-                self.pebbles_after_blinks(pebble * 2024, blinks - 1)
-            };
+                Ok(sum)
+            }
+        } else {
+            a.checked_add(b)
+                .ok_or_else(|| "Pebble count overflowed; try counting with a modulus".into())
+        }
+    }
+}
 
-            self.cache.insert((pebble, blinks), pebbles);
+/// How each pebble changes when we blink, described as comma-separated `key=value` pairs (for
+/// example, `zero=1,split=2,base=10,multiplier=2024`):
+///
+/// - `zero`: the number engraved on a pebble that was engraved with 0
+/// - `split`: pebbles whose digit count is a multiple of this (at least 2) split into this many
+///   pebbles, each with an equal share of the digits; `never` disables splitting
+/// - `base`: the base in which digits are counted and split
+/// - `multiplier`: what every other pebble's number is multiplied by
+///
+/// Omitted keys keep the puzzle's rules.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct BlinkRules {
+    zero: u64,
+    split: Option<u32>,
+    base: u64,
+    multiplier: u64,
+}
 
-            pebbles
+impl BlinkRules {
+    pub fn apply(&self, pebble: u64) -> Result<Vec<u64>, Box<dyn Error>> {
+        if pebble == 0 {
+            Ok(vec![self.zero])
+        } else if let Some(parts) = self
+            .split
+            .filter(|&parts| self.digits(pebble).is_multiple_of(parts))
+        {
+            Ok(self.split_pebble(pebble, parts))
+        } else {
+            Ok(vec![pebble.checked_mul(self.multiplier).ok_or_else(
+                || format!("Pebble {} overflowed when multiplied", pebble),
+            )?])
         }
     }
 
-    fn has_even_decimal_digits(pebble: u64) -> bool {
-        pebble.ilog10() % 2 != 0
+    fn digits(&self, pebble: u64) -> u32 {
+        pebble.ilog(self.base) + 1
     }
 
-    fn split_pebble(pebble: u64) -> (u64, u64) {
-        let mask = 10u64.pow((pebble.ilog10() + 1) / 2);
+    fn split_pebble(&self, pebble: u64, parts: u32) -> Vec<u64> {
+        let mask = self.base.pow(self.digits(pebble) / parts);
+        let mut remaining = pebble;
+
+        let mut pebbles: Vec<u64> = (0..parts)
+            .map(|_| {
+                let part = remaining % mask;
+                remaining /= mask;
 
-        let left = pebble / mask;
-        let right = pebble % (left * mask);
+                part
+            })
+            .collect();
 
-        (left, right)
+        pebbles.reverse();
+        pebbles
+    }
+}
+
+impl Default for BlinkRules {
+    fn default() -> Self {
+        BlinkRules {
+            zero: 1,
+            split: Some(2),
+            base: 10,
+            multiplier: 2024,
+        }
+    }
+}
+
+impl Display for BlinkRules {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "zero={},split=", self.zero)?;
+
+        if let Some(split) = self.split {
+            write!(f, "{}", split)?;
+        } else {
+            write!(f, "never")?;
+        }
+
+        write!(f, ",base={},multiplier={}", self.base, self.multiplier)
+    }
+}
+
+impl FromStr for BlinkRules {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = BlinkRules::default();
+
+        for rule in s.split(',').map(str::trim).filter(|rule| !rule.is_empty()) {
+            let (key, value) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("Rule must be a key=value pair: {}", rule))?;
+
+            match key.trim() {
+                "zero" => rules.zero = value.trim().parse()?,
+                "split" => {
+                    rules.split = match value.trim() {
+                        "never" => None,
+                        value => Some(value.parse()?),
+                    }
+                }
+                "base" => rules.base = value.trim().parse()?,
+                "multiplier" => rules.multiplier = value.trim().parse()?,
+                key => return Err(format!("Unrecognized rule: {}", key).into()),
+            }
+        }
+
+        if rules.base < 2 {
+            Err("Base must be at least 2".into())
+        } else if rules.split.is_some_and(|split| split < 2) {
+            Err("Pebbles must split into at least two parts".into())
+        } else {
+            Ok(rules)
+        }
     }
 }
 
@@ -88,7 +261,9 @@ impl FromStr for PebbleLine {
 
         Ok(PebbleLine {
             pebbles,
-            cache: HashMap::new(),
+            rules: BlinkRules::default(),
+            modulus: None,
+            transitions: HashMap::new(),
         })
     }
 }
@@ -100,23 +275,83 @@ mod test {
     const TEST_PEBBLE_LINE: &str = "125 17";
 
     #[test]
-    fn test_has_even_decimal_digits() {
-        assert!(!PebbleLine::has_even_decimal_digits(1));
-        assert!(PebbleLine::has_even_decimal_digits(12));
-        assert!(!PebbleLine::has_even_decimal_digits(123));
+    fn test_digits() {
+        let rules = BlinkRules::default();
+
+        assert_eq!(1, rules.digits(1));
+        assert_eq!(2, rules.digits(12));
+        assert_eq!(3, rules.digits(123));
     }
 
     #[test]
     fn test_split_pebble() {
-        assert_eq!((1, 2), PebbleLine::split_pebble(12));
-        assert_eq!((123, 456), PebbleLine::split_pebble(123456));
+        let rules = BlinkRules::default();
+
+        assert_eq!(vec![1, 2], rules.split_pebble(12, 2));
+        assert_eq!(vec![123, 456], rules.split_pebble(123456, 2));
+        assert_eq!(vec![12, 34, 56], rules.split_pebble(123456, 3));
+        assert_eq!(vec![10, 0], rules.split_pebble(1000, 2));
     }
 
     #[test]
     fn test_total_pebbles_after_blinks() {
         let mut pebble_line = PebbleLine::from_str(TEST_PEBBLE_LINE).unwrap();
 
-        assert_eq!(22, pebble_line.total_pebbles_after_blinks(6));
-        assert_eq!(55312, pebble_line.total_pebbles_after_blinks(25));
+        assert_eq!(22, pebble_line.total_pebbles_after_blinks(6).unwrap());
+        assert_eq!(55312, pebble_line.total_pebbles_after_blinks(25).unwrap());
+    }
+
+    #[test]
+    fn test_total_pebbles_with_modulus() {
+        let mut pebble_line = PebbleLine::from_str(TEST_PEBBLE_LINE).unwrap();
+        let exact = pebble_line.total_pebbles_after_blinks(75).unwrap();
+
+        // Counts outgrow even a u128 well before a thousand blinks
+        assert!(pebble_line.total_pebbles_after_blinks(1000).is_err());
+
+        let mut pebble_line = pebble_line.with_modulus(Some(1_000_000_007)).unwrap();
+
+        assert_eq!(
+            exact % 1_000_000_007,
+            pebble_line.total_pebbles_after_blinks(75).unwrap()
+        );
+
+        assert!(pebble_line.total_pebbles_after_blinks(1000).unwrap() < 1_000_000_007);
+
+        // Duplicate pebbles are counted modulo the modulus from the start
+        let mut pebble_line = PebbleLine::from_str("5 5")
+            .unwrap()
+            .with_modulus(Some(1))
+            .unwrap();
+
+        assert_eq!(0, pebble_line.total_pebbles_after_blinks(0).unwrap());
+        assert_eq!(0, pebble_line.total_pebbles_after_blinks(3).unwrap());
+    }
+
+    #[test]
+    fn test_blink_rules() {
+        let rules = BlinkRules::from_str("split=3, multiplier=3").unwrap();
+
+        assert_eq!("zero=1,split=3,base=10,multiplier=3", rules.to_string());
+        assert_eq!(vec![1], rules.apply(0).unwrap());
+        assert_eq!(vec![1, 2, 3], rules.apply(123).unwrap());
+        assert_eq!(vec![36], rules.apply(12).unwrap());
+
+        // In binary, 0b1101 splits into 0b11 and 0b01
+        let rules = BlinkRules::from_str("base=2").unwrap();
+        assert_eq!(vec![3, 1], rules.apply(13).unwrap());
+
+        let rules = BlinkRules::from_str("split=never,multiplier=1").unwrap();
+        let mut pebble_line = PebbleLine::from_str(TEST_PEBBLE_LINE)
+            .unwrap()
+            .with_rules(rules);
+
+        assert_eq!(2, pebble_line.total_pebbles_after_blinks(5000).unwrap());
+
+        assert!(BlinkRules::from_str("base=1").is_err());
+        assert!(BlinkRules::from_str("split=0").is_err());
+        assert!(BlinkRules::from_str("split=1").is_err());
+        assert!(BlinkRules::from_str("speed=2").is_err());
+        assert!(BlinkRules::from_str("multiplier").is_err());
     }
 }
@@ -1,12 +1,42 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::error::Error;
-use std::ops::RangeInclusive;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::str::FromStr;
 use std::{env, fs};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut path = None;
+    let mut svg_path = None;
+    let mut geojson_path = None;
+    let mut scale = 10;
+    let mut report = false;
+    let mut sort_column = None;
+    let mut descending = false;
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--svg" => svg_path = Some(args.next().ok_or("Missing SVG path")?),
+            "--geojson" => geojson_path = Some(args.next().ok_or("Missing GeoJSON path")?),
+            "--scale" => scale = args.next().ok_or("Missing scale")?.parse()?,
+            "--report" => report = true,
+            // Sorting only makes sense for the report, so asking for one implies the other
+            "--sort" => {
+                sort_column = Some(args.next().ok_or("Missing sort column")?.parse()?);
+                report = true;
+            }
+            "--descending" => {
+                descending = true;
+                report = true;
+            }
+            _ => path = Some(arg),
+        }
+    }
 
-    if let Some(path) = args.get(1) {
+    if let Some(path) = path {
         let garden_map = GardenMap::from_str(fs::read_to_string(path)?.as_str())?;
 
         println!("Fencing cost: {}", garden_map.fencing_cost());
@@ -16,159 +46,534 @@ fn main() -> Result<(), Box<dyn Error>> {
             garden_map.fencing_cost_with_discount()
         );
 
+        if report {
+            let mut reports = garden_map.report();
+
+            if let Some(sort_column) = sort_column {
+                ReportColumn::sort(&mut reports, sort_column, descending);
+            }
+
+            print_report(&reports);
+        }
+
+        if let Some(svg_path) = svg_path {
+            garden_map.write_svg(scale, &mut BufWriter::new(File::create(&svg_path)?))?;
+            println!("Wrote regions to {}", svg_path);
+        }
+
+        if let Some(geojson_path) = geojson_path {
+            garden_map.write_geojson(&mut BufWriter::new(File::create(&geojson_path)?))?;
+            println!("Wrote regions to {}", geojson_path);
+        }
+
         Ok(())
     } else {
-        Err("Usage: day12 INPUT_FILE_PATH".into())
+        Err(
+            "Usage: day12 [--report] [--sort COLUMN] [--descending] [--svg SVG_PATH] \
+            [--geojson GEOJSON_PATH] [--scale N] INPUT_FILE_PATH"
+                .into(),
+        )
     }
 }
 
+fn print_report(reports: &[RegionReport]) {
+    println!(
+        "{:>6}  {:<5}  {:>6}  {:>9}  {:>6}  {:>8}  {:>10}  {:<17}  Encloses",
+        "Region", "Plant", "Area", "Perimeter", "Sides", "Price", "Discounted", "Bounding box"
+    );
+
+    for report in reports {
+        let ((x_min, y_min), (x_max, y_max)) = report.bounding_box;
+
+        println!(
+            "{:>6}  {:<5}  {:>6}  {:>9}  {:>6}  {:>8}  {:>10}  {:<17}  {}",
+            report.index,
+            report.region.vegetable,
+            report.region.area,
+            report.region.perimeter,
+            report.region.sides,
+            report.region.price(),
+            report.region.discounted_price(),
+            format!("({},{})-({},{})", x_min, y_min, x_max, y_max),
+            if report.encloses_regions { "yes" } else { "no" }
+        );
+    }
+
+    println!(
+        "Total: {} regions, price {}, discounted price {}",
+        reports.len(),
+        reports
+            .iter()
+            .map(|report| report.region.price())
+            .sum::<u32>(),
+        reports
+            .iter()
+            .map(|report| report.region.discounted_price())
+            .sum::<u32>()
+    );
+}
+
 struct GardenMap {
     vegetables: Vec<char>,
     width: usize,
 }
 
-impl GardenMap {
-    pub fn fencing_cost(&self) -> u32 {
-        self.regions()
-            .iter()
-            .map(|region| {
-                let vegetable = self.vegetables[*region.first().unwrap()];
+/// A connected group of plots growing the same vegetable
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Region {
+    vegetable: char,
+    area: u32,
+    perimeter: u32,
+    sides: u32,
+}
 
-                let perimeter: u32 = region
-                    .iter()
-                    .map(|&i| {
-                        4 - self
-                            .neighbors(i)
-                            .iter()
-                            .filter(|&&n| self.vegetables[n] == vegetable)
-                            .count() as u32
-                    })
-                    .sum();
+impl Region {
+    pub fn price(&self) -> u32 {
+        self.area * self.perimeter
+    }
 
-                region.len() as u32 * perimeter
-            })
-            .sum()
+    pub fn discounted_price(&self) -> u32 {
+        self.area * self.sides
+    }
+}
+
+/// A region's measurements along with where it lies on the map
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct RegionReport {
+    /// The order in which the region's first plot appears on the map
+    index: usize,
+    region: Region,
+    /// The top left and bottom right plots of the region's bounding box
+    bounding_box: ((usize, usize), (usize, usize)),
+    encloses_regions: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ReportColumn {
+    Region,
+    Plant,
+    Area,
+    Perimeter,
+    Sides,
+    Price,
+    DiscountedPrice,
+    BoundingBox,
+    Encloses,
+}
+
+impl ReportColumn {
+    const ALL: [ReportColumn; 9] = [
+        ReportColumn::Region,
+        ReportColumn::Plant,
+        ReportColumn::Area,
+        ReportColumn::Perimeter,
+        ReportColumn::Sides,
+        ReportColumn::Price,
+        ReportColumn::DiscountedPrice,
+        ReportColumn::BoundingBox,
+        ReportColumn::Encloses,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportColumn::Region => "region",
+            ReportColumn::Plant => "plant",
+            ReportColumn::Area => "area",
+            ReportColumn::Perimeter => "perimeter",
+            ReportColumn::Sides => "sides",
+            ReportColumn::Price => "price",
+            ReportColumn::DiscountedPrice => "discounted-price",
+            ReportColumn::BoundingBox => "bounding-box",
+            ReportColumn::Encloses => "encloses",
+        }
+    }
+
+    pub fn compare(&self, a: &RegionReport, b: &RegionReport) -> Ordering {
+        match self {
+            ReportColumn::Region => a.index.cmp(&b.index),
+            ReportColumn::Plant => a.region.vegetable.cmp(&b.region.vegetable),
+            ReportColumn::Area => a.region.area.cmp(&b.region.area),
+            ReportColumn::Perimeter => a.region.perimeter.cmp(&b.region.perimeter),
+            ReportColumn::Sides => a.region.sides.cmp(&b.region.sides),
+            ReportColumn::Price => a.region.price().cmp(&b.region.price()),
+            ReportColumn::DiscountedPrice => a
+                .region
+                .discounted_price()
+                .cmp(&b.region.discounted_price()),
+            // Bounding boxes sort by their top left plot, in reading order
+            ReportColumn::BoundingBox => {
+                let ((ax, ay), _) = a.bounding_box;
+                let ((bx, by), _) = b.bounding_box;
+
+                (ay, ax).cmp(&(by, bx))
+            }
+            ReportColumn::Encloses => a.encloses_regions.cmp(&b.encloses_regions),
+        }
+    }
+
+    /// Sorts reports by this column, breaking ties by the order in which regions appear.
+    pub fn sort(reports: &mut [RegionReport], column: ReportColumn, descending: bool) {
+        reports.sort_by(|a, b| {
+            let ordering = column.compare(a, b);
+
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+            .then(a.index.cmp(&b.index))
+        });
+    }
+}
+
+impl FromStr for ReportColumn {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ReportColumn::ALL
+            .into_iter()
+            .find(|column| column.name() == s)
+            .ok_or_else(|| format!("Unrecognized report column: {}", s).into())
+    }
+}
+
+/// A corner of a plot, where (0, 0) is the top left corner of the map
+type Corner = (usize, usize);
+
+/// A region's boundary, traced as closed rings of corners. The outer boundary comes first and runs
+/// clockwise on the map (with y pointing down), and any holes follow and run counterclockwise.
+struct RegionPolygon {
+    region: Region,
+    rings: Vec<Vec<Corner>>,
+}
+
+impl GardenMap {
+    pub fn fencing_cost(&self) -> u32 {
+        self.regions().iter().map(Region::price).sum()
     }
 
     pub fn fencing_cost_with_discount(&self) -> u32 {
-        self.regions()
-            .iter()
-            .map(|region| self.sides(region) * region.len() as u32)
-            .sum()
+        self.regions().iter().map(Region::discounted_price).sum()
     }
 
-    fn sides(&self, region: &[usize]) -> u32 {
-        let mut sides = 0;
-        let (x_range, y_range) = self.bounding_box(region);
+    /// Measures every region in a single pass over the map, in the order in which each region's
+    /// first plot appears.
+    pub fn regions(&self) -> Vec<Region> {
+        let labels = self.region_labels();
+        let mut regions: Vec<Region> = Vec::new();
+
+        for (i, &label) in labels.iter().enumerate() {
+            if label == regions.len() {
+                regions.push(Region {
+                    vegetable: self.vegetables[i],
+                    area: 0,
+                    perimeter: 0,
+                    sides: 0,
+                });
+            }
 
-        // Find horizontal sides
-        for y in y_range.clone() {
-            let mut top_fences = Vec::with_capacity(self.width);
-            let mut bottom_fences = Vec::with_capacity(self.width);
+            let region = &mut regions[label];
+            region.area += 1;
 
-            for x in x_range.clone() {
-                if region.contains(&self.index((x, y))) {
-                    top_fences.push(y == 0 || !region.contains(&self.index((x, y - 1))));
-                    bottom_fences
-                        .push(y == self.height() - 1 || !region.contains(&self.index((x, y + 1))));
-                } else {
-                    top_fences.push(false);
-                    bottom_fences.push(false);
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                if !self.same_region(&labels, i, dx, dy) {
+                    region.perimeter += 1;
                 }
             }
 
-            sides += Self::segments(&top_fences) + Self::segments(&bottom_fences);
+            // A region has as many sides as it has corners; each plot contributes an outside corner
+            // where both neighbors toward a diagonal are outside the region, and an inside corner
+            // where both are inside but the diagonal plot isn't
+            for (dx, dy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                let horizontal = self.same_region(&labels, i, dx, 0);
+                let vertical = self.same_region(&labels, i, 0, dy);
+
+                if (!horizontal && !vertical)
+                    || (horizontal && vertical && !self.same_region(&labels, i, dx, dy))
+                {
+                    region.sides += 1;
+                }
+            }
         }
 
-        // Find vertical sides
-        for x in x_range.clone() {
-            let mut left_fences = Vec::with_capacity(self.height());
-            let mut right_fences = Vec::with_capacity(self.height());
+        regions
+    }
 
-            for y in y_range.clone() {
-                if region.contains(&self.index((x, y))) {
-                    left_fences.push(x == 0 || !region.contains(&self.index((x - 1, y))));
-                    right_fences
-                        .push(x == self.width - 1 || !region.contains(&self.index((x + 1, y))));
-                } else {
-                    left_fences.push(false);
-                    right_fences.push(false);
+    /// Lists every region with its prices, bounding box and whether it surrounds other regions, in
+    /// the order in which each region's first plot appears.
+    pub fn report(&self) -> Vec<RegionReport> {
+        self.polygons()
+            .into_iter()
+            .enumerate()
+            .map(|(index, polygon)| {
+                // Corners lie on plot edges, so the far corner is just past the last plot
+                let outer = &polygon.rings[0];
+                let x_min = outer.iter().map(|&(x, _)| x).min().unwrap();
+                let x_max = outer.iter().map(|&(x, _)| x).max().unwrap() - 1;
+                let y_min = outer.iter().map(|&(_, y)| y).min().unwrap();
+                let y_max = outer.iter().map(|&(_, y)| y).max().unwrap() - 1;
+
+                RegionReport {
+                    index,
+                    region: polygon.region,
+                    bounding_box: ((x_min, y_min), (x_max, y_max)),
+                    // Every hole in a region is filled by at least one other region
+                    encloses_regions: polygon.rings.len() > 1,
                 }
-            }
+            })
+            .collect()
+    }
 
-            sides += Self::segments(&left_fences) + Self::segments(&right_fences);
+    /// Traces the boundary of every region into polygons with a corner at each change of direction,
+    /// so a region's rings have as many corners in total as the region has sides.
+    pub fn polygons(&self) -> Vec<RegionPolygon> {
+        let labels = self.region_labels();
+        let regions = self.regions();
+        let mut edges_by_region: Vec<Vec<(Corner, Corner)>> = vec![Vec::new(); regions.len()];
+
+        // Walk around every plot clockwise, keeping the edges that face another region
+        for (i, &label) in labels.iter().enumerate() {
+            let (x, y) = self.position(i);
+
+            for (dx, dy, from, to) in [
+                (0, -1, (x, y), (x + 1, y)),
+                (1, 0, (x + 1, y), (x + 1, y + 1)),
+                (0, 1, (x + 1, y + 1), (x, y + 1)),
+                (-1, 0, (x, y + 1), (x, y)),
+            ] {
+                if !self.same_region(&labels, i, dx, dy) {
+                    edges_by_region[label].push((from, to));
+                }
+            }
         }
 
-        sides
+        regions
+            .into_iter()
+            .zip(edges_by_region)
+            .map(|(region, edges)| {
+                let mut rings = Self::trace_rings(&edges);
+
+                // The outer boundary is the only ring with a positive (clockwise) area
+                let outer = rings
+                    .iter()
+                    .position(|ring| Self::signed_area(ring) > 0)
+                    .unwrap();
+
+                rings.swap(0, outer);
+
+                RegionPolygon { region, rings }
+            })
+            .collect()
     }
 
-    fn segments(fences: &[bool]) -> u32 {
-        let mut segments = 0;
+    /// Joins directed edges end to end into closed rings, dropping corners between collinear edges.
+    fn trace_rings(edges: &[(Corner, Corner)]) -> Vec<Vec<Corner>> {
+        let mut edges_by_start: HashMap<Corner, Vec<usize>> = HashMap::new();
 
-        let mut previous = false;
+        for (i, &(from, _)) in edges.iter().enumerate() {
+            edges_by_start.entry(from).or_default().push(i);
+        }
+
+        let direction = |(from, to): (Corner, Corner)| {
+            (
+                to.0 as isize - from.0 as isize,
+                to.1 as isize - from.1 as isize,
+            )
+        };
+
+        let mut visited = vec![false; edges.len()];
+        let mut rings = Vec::new();
 
-        for &fence in fences {
-            if previous && !fence {
-                segments += 1;
+        for first in 0..edges.len() {
+            if visited[first] {
+                continue;
             }
 
-            previous = fence;
-        }
+            let mut ring = Vec::new();
+            let mut edge = first;
+
+            loop {
+                visited[edge] = true;
 
-        if previous {
-            segments += 1;
+                let (dx, dy) = direction(edges[edge]);
+                let candidates = &edges_by_start[&edges[edge].1];
+
+                // Where two regions touch diagonally, two edges leave the same corner; turning left
+                // follows the neighboring plot so rings touch at the corner instead of crossing
+                let next = if candidates.len() == 1 {
+                    candidates[0]
+                } else {
+                    *candidates
+                        .iter()
+                        .find(|&&candidate| direction(edges[candidate]) == (dy, -dx))
+                        .unwrap()
+                };
+
+                if direction(edges[next]) != (dx, dy) {
+                    ring.push(edges[edge].1);
+                }
+
+                if next == first {
+                    break;
+                }
+
+                edge = next;
+            }
+
+            rings.push(ring);
         }
 
-        segments
+        rings
     }
 
-    fn bounding_box(&self, region: &[usize]) -> (RangeInclusive<usize>, RangeInclusive<usize>) {
-        let mut x_min = usize::MAX;
-        let mut x_max = usize::MIN;
-        let mut y_min = usize::MAX;
-        let mut y_max = usize::MIN;
+    /// Twice the area enclosed by a ring, positive when the ring runs clockwise on the map
+    fn signed_area(ring: &[Corner]) -> i64 {
+        ring.iter()
+            .zip(ring.iter().cycle().skip(1))
+            .map(|(&(x1, y1), &(x2, y2))| (x1 * y2) as i64 - (x2 * y1) as i64)
+            .sum()
+    }
 
-        region.iter().map(|&i| self.position(i)).for_each(|(x, y)| {
-            x_min = x_min.min(x);
-            x_max = x_max.max(x);
-            y_min = y_min.min(y);
-            y_max = y_max.max(y);
-        });
+    /// Writes the regions as an SVG image with one path per region, filled by vegetable.
+    pub fn write_svg(&self, scale: usize, writer: &mut impl Write) -> Result<(), Box<dyn Error>> {
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+            self.width * scale,
+            self.height() * scale,
+            self.width,
+            self.height()
+        )?;
+
+        for polygon in self.polygons() {
+            let path: Vec<String> = polygon
+                .rings
+                .iter()
+                .map(|ring| {
+                    let corners: Vec<String> =
+                        ring.iter().map(|(x, y)| format!("{} {}", x, y)).collect();
+
+                    format!("M {} Z", corners.join(" L "))
+                })
+                .collect();
+
+            writeln!(
+                writer,
+                r#"  <path d="{}" fill="{}" fill-rule="evenodd" stroke="black" stroke-width="0.05"><title>{}: area {}, perimeter {}, sides {}</title></path>"#,
+                path.join(" "),
+                color(polygon.region.vegetable),
+                escape_xml(polygon.region.vegetable),
+                polygon.region.area,
+                polygon.region.perimeter,
+                polygon.region.sides
+            )?;
+        }
 
-        (x_min..=x_max, y_min..=y_max)
+        writeln!(writer, "</svg>")?;
+
+        Ok(())
     }
 
-    fn regions(&self) -> Vec<Vec<usize>> {
-        let mut mapped_regions = vec![false; self.vegetables.len()];
-        let mut regions = Vec::new();
+    /// Writes the regions as a GeoJSON feature collection of polygons in map coordinates, with
+    /// each region's measurements as properties.
+    pub fn write_geojson(&self, writer: &mut impl Write) -> Result<(), Box<dyn Error>> {
+        let features: Vec<String> = self
+            .polygons()
+            .iter()
+            .map(|polygon| {
+                // GeoJSON rings repeat their first position at the end
+                let rings: Vec<String> = polygon
+                    .rings
+                    .iter()
+                    .map(|ring| {
+                        let positions: Vec<String> = ring
+                            .iter()
+                            .chain(ring.first())
+                            .map(|(x, y)| format!("[{},{}]", x, y))
+                            .collect();
 
-        while let Some(start) = mapped_regions.iter().position(|mapped| !mapped) {
-            let vegetable = self.vegetables[start];
+                        format!("[{}]", positions.join(","))
+                    })
+                    .collect();
+
+                format!(
+                    r#"{{"type":"Feature","properties":{{"vegetable":"{}","area":{},"perimeter":{},"sides":{}}},"geometry":{{"type":"Polygon","coordinates":[{}]}}}}"#,
+                    escape_json(polygon.region.vegetable),
+                    polygon.region.area,
+                    polygon.region.perimeter,
+                    polygon.region.sides,
+                    rings.join(",")
+                )
+            })
+            .collect();
 
-            let mut stack = vec![start];
-            let mut explored = mapped_regions.clone();
+        writeln!(
+            writer,
+            r#"{{"type":"FeatureCollection","features":[{}]}}"#,
+            features.join(",")
+        )?;
 
-            let mut region = Vec::new();
+        Ok(())
+    }
 
-            while let Some(i) = stack.pop() {
-                if explored[i] {
-                    continue;
-                } else {
-                    explored[i] = true;
-                }
+    /// Labels each plot with the index of its region, numbering regions in the order in which
+    /// their first plot appears.
+    fn region_labels(&self) -> Vec<usize> {
+        // A union-find forest of plots in which each region's root is its first plot
+        let mut parents: Vec<usize> = (0..self.vegetables.len()).collect();
 
-                if self.vegetables[i] == vegetable {
-                    mapped_regions[i] = true;
-                    region.push(i);
+        for i in 0..self.vegetables.len() {
+            let (x, y) = self.position(i);
+            let left = (x > 0).then(|| i - 1);
+            let up = (y > 0).then(|| i - self.width);
 
-                    stack.extend(self.neighbors(i).iter());
+            for neighbor in [left, up].into_iter().flatten() {
+                if self.vegetables[neighbor] == self.vegetables[i] {
+                    let a = Self::root(&mut parents, i);
+                    let b = Self::root(&mut parents, neighbor);
+
+                    parents[a.max(b)] = a.min(b);
                 }
             }
+        }
+
+        // Roots come before the rest of their region, so their labels are always ready in time
+        let mut labels = vec![0; self.vegetables.len()];
+        let mut regions = 0;
 
-            regions.push(region);
+        for i in 0..self.vegetables.len() {
+            let root = Self::root(&mut parents, i);
+
+            if root == i {
+                labels[i] = regions;
+                regions += 1;
+            } else {
+                labels[i] = labels[root];
+            }
         }
 
-        regions
+        labels
+    }
+
+    /// Finds the root of a plot's tree, pointing plots along the way at their grandparents
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+
+        i
+    }
+
+    fn same_region(&self, labels: &[usize], index: usize, dx: isize, dy: isize) -> bool {
+        let (x, y) = self.position(index);
+        let (x, y) = (x as isize + dx, y as isize + dy);
+
+        x >= 0
+            && y >= 0
+            && (x as usize) < self.width
+            && (y as usize) < self.height()
+            && labels[self.index((x as usize, y as usize))] == labels[index]
     }
 
     fn height(&self) -> usize {
@@ -182,29 +587,29 @@ impl GardenMap {
     fn index(&self, position: (usize, usize)) -> usize {
         (self.width * position.1) + position.0
     }
+}
 
-    fn neighbors(&self, index: usize) -> Vec<usize> {
-        let (x, y) = self.position(index);
-
-        let mut neighbors = Vec::with_capacity(4);
-
-        if x > 0 {
-            neighbors.push(index - 1);
-        }
-
-        if x < self.width - 1 {
-            neighbors.push(index + 1);
-        }
-
-        if y > 0 {
-            neighbors.push(index - self.width);
-        }
+/// Picks a fill color for a vegetable, spreading letters around the color wheel
+fn color(vegetable: char) -> String {
+    format!("hsl({}, 60%, 65%)", (vegetable as u32 * 137) % 360)
+}
 
-        if y < self.height() - 1 {
-            neighbors.push(index + self.width);
-        }
+fn escape_xml(vegetable: char) -> String {
+    match vegetable {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '"' => "&quot;".to_string(),
+        _ => vegetable.to_string(),
+    }
+}
 
-        neighbors
+fn escape_json(vegetable: char) -> String {
+    match vegetable {
+        '"' => "\\\"".to_string(),
+        '\\' => "\\\\".to_string(),
+        c if c.is_control() => format!("\\u{:04x}", c as u32),
+        _ => vegetable.to_string(),
     }
 }
 
@@ -257,4 +662,217 @@ mod test {
         let garden_map = GardenMap::from_str(TEST_MAP).unwrap();
         assert_eq!(1206, garden_map.fencing_cost_with_discount());
     }
+
+    #[test]
+    fn test_regions() {
+        let garden_map = GardenMap::from_str(indoc! {"
+            OOOOO
+            OXOXO
+            OOOOO
+            OXOXO
+            OOOOO
+        "})
+        .unwrap();
+
+        let regions = garden_map.regions();
+
+        assert_eq!(5, regions.len());
+        assert_eq!(
+            Region {
+                vegetable: 'O',
+                area: 21,
+                perimeter: 36,
+                sides: 20,
+            },
+            regions[0]
+        );
+
+        assert!(regions[1..].iter().all(|region| *region
+            == Region {
+                vegetable: 'X',
+                area: 1,
+                perimeter: 4,
+                sides: 4,
+            }));
+
+        assert_eq!(772, garden_map.fencing_cost());
+        assert_eq!(436, garden_map.fencing_cost_with_discount());
+
+        // Diagonally adjacent plots belong to separate regions, and each has four inside corners
+        let garden_map = GardenMap::from_str(indoc! {"
+            AAAAAA
+            AAABBA
+            AAABBA
+            ABBAAA
+            ABBAAA
+            AAAAAA
+        "})
+        .unwrap();
+
+        assert_eq!(3, garden_map.regions().len());
+        assert_eq!(368, garden_map.fencing_cost_with_discount());
+    }
+
+    #[test]
+    fn test_regions_large_map() {
+        // A 500x500 checkerboard has a region for every plot
+        let map: String = (0..500)
+            .map(|y| {
+                (0..500)
+                    .map(|x| if (x + y) % 2 == 0 { 'A' } else { 'B' })
+                    .chain(std::iter::once('\n'))
+                    .collect::<String>()
+            })
+            .collect();
+
+        let garden_map = GardenMap::from_str(&map).unwrap();
+
+        assert_eq!(250_000, garden_map.regions().len());
+        assert_eq!(1_000_000, garden_map.fencing_cost());
+        assert_eq!(1_000_000, garden_map.fencing_cost_with_discount());
+    }
+
+    #[test]
+    fn test_polygons() {
+        let garden_map = GardenMap::from_str(indoc! {"
+            AAAAAA
+            AAABBA
+            AAABBA
+            ABBAAA
+            ABBAAA
+            AAAAAA
+        "})
+        .unwrap();
+
+        let polygons = garden_map.polygons();
+
+        // The two B regions touch diagonally, so they leave separate holes in the A region
+        assert_eq!(
+            vec![
+                vec![(6, 0), (6, 6), (0, 6), (0, 0)],
+                vec![(3, 1), (3, 3), (5, 3), (5, 1)],
+                vec![(1, 3), (1, 5), (3, 5), (3, 3)],
+            ],
+            polygons[0].rings
+        );
+
+        assert_eq!(
+            vec![vec![(5, 1), (5, 3), (3, 3), (3, 1)]],
+            polygons[1].rings
+        );
+
+        // Every region has one corner per side
+        for garden_map in [garden_map, GardenMap::from_str(TEST_MAP).unwrap()] {
+            for polygon in garden_map.polygons() {
+                assert_eq!(
+                    polygon.region.sides as usize,
+                    polygon.rings.iter().map(Vec::len).sum::<usize>()
+                );
+
+                assert_eq!(
+                    polygon.region.area as i64 * 2,
+                    polygon
+                        .rings
+                        .iter()
+                        .map(|ring| GardenMap::signed_area(ring))
+                        .sum::<i64>()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_geojson() {
+        let garden_map = GardenMap::from_str(indoc! {"
+            AB
+            BB
+        "})
+        .unwrap();
+
+        let mut geojson = Vec::new();
+        garden_map.write_geojson(&mut geojson).unwrap();
+
+        assert_eq!(
+            concat!(
+                r#"{"type":"FeatureCollection","features":["#,
+                r#"{"type":"Feature","properties":{"vegetable":"A","area":1,"perimeter":4,"sides":4},"#,
+                r#""geometry":{"type":"Polygon","coordinates":[[[1,0],[1,1],[0,1],[0,0],[1,0]]]}},"#,
+                r#"{"type":"Feature","properties":{"vegetable":"B","area":3,"perimeter":8,"sides":6},"#,
+                r#""geometry":{"type":"Polygon","coordinates":[[[2,0],[2,2],[0,2],[0,1],[1,1],[1,0],[2,0]]]}}"#,
+                "]}\n"
+            ),
+            String::from_utf8(geojson).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let garden_map = GardenMap::from_str(indoc! {"
+            OOOOO
+            OXOXO
+            OOOOO
+            OXOXO
+            OOOOO
+        "})
+        .unwrap();
+
+        let mut reports = garden_map.report();
+
+        assert_eq!(
+            RegionReport {
+                index: 0,
+                region: Region {
+                    vegetable: 'O',
+                    area: 21,
+                    perimeter: 36,
+                    sides: 20,
+                },
+                bounding_box: ((0, 0), (4, 4)),
+                encloses_regions: true,
+            },
+            reports[0]
+        );
+
+        assert_eq!(
+            RegionReport {
+                index: 4,
+                region: Region {
+                    vegetable: 'X',
+                    area: 1,
+                    perimeter: 4,
+                    sides: 4,
+                },
+                bounding_box: ((3, 3), (3, 3)),
+                encloses_regions: false,
+            },
+            reports[4]
+        );
+
+        ReportColumn::sort(&mut reports, ReportColumn::Price, false);
+        assert_eq!(vec![1, 2, 3, 4, 0], indices(&reports));
+
+        ReportColumn::sort(&mut reports, ReportColumn::Plant, true);
+        assert_eq!(vec![1, 2, 3, 4, 0], indices(&reports));
+
+        ReportColumn::sort(&mut reports, ReportColumn::Region, false);
+        assert_eq!(vec![0, 1, 2, 3, 4], indices(&reports));
+
+        // The worked example's R region costs 216 and 120 with the discount
+        let reports = GardenMap::from_str(TEST_MAP).unwrap().report();
+
+        assert_eq!('R', reports[0].region.vegetable);
+        assert_eq!(216, reports[0].region.price());
+        assert_eq!(120, reports[0].region.discounted_price());
+        assert_eq!(((0, 0), (4, 3)), reports[0].bounding_box);
+
+        assert_eq!(
+            Ok(ReportColumn::DiscountedPrice),
+            ReportColumn::from_str("discounted-price").map_err(|error| error.to_string())
+        );
+        assert!(ReportColumn::from_str("cost").is_err());
+    }
+
+    fn indices(reports: &[RegionReport]) -> Vec<usize> {
+        reports.iter().map(|report| report.index).collect()
+    }
 }
@@ -1,46 +1,111 @@
 use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 use std::{env, fs};
 
-const BUTTON_A_TOKENS: i64 = 3;
-const BUTTON_B_TOKENS: i64 = 1;
+// Default token costs for buttons that don't list their own cost
+const BUTTON_A_TOKENS: u64 = 3;
+const BUTTON_TOKENS: u64 = 1;
 
 const UNIT_CORRECTION: i64 = 10000000000000;
 
+// Machines with more than two buttons search over presses of the extra buttons, and the number of
+// combinations grows exponentially with the number of buttons; beyond this many, we give up
+const MAX_SEARCH_COMBINATIONS: u128 = 100_000_000;
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut path = None;
+    let mut press_limit = Some(100);
+    let mut report = false;
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--press-limit" => {
+                press_limit = Some(args.next().ok_or("Missing press limit")?.parse()?)
+            }
+            "--no-press-limit" => press_limit = None,
+            "--report" => report = true,
+            _ => path = Some(arg),
+        }
+    }
+
+    if let Some(path) = path {
+        let claw_machines: Vec<ClawMachine> =
+            ClawMachine::machines_from_str(fs::read_to_string(&path)?.as_str())?
+                .into_iter()
+                .map(|machine| machine.with_press_limit(press_limit))
+                .collect();
 
-    if let Some(path) = args.get(1) {
         println!(
             "Min tokens to win all possible prizes: {}",
-            ClawMachine::machines_from_str(fs::read_to_string(path)?.as_str())?
+            claw_machines
                 .iter()
                 .map(|machine| machine.min_tokens_to_win().unwrap_or(0))
                 .sum::<u64>()
         );
 
+        if report {
+            print_report(&claw_machines);
+        }
+
+        let claw_machines = ClawMachine::machines_from_str_with_unit_correction(
+            fs::read_to_string(&path)?.as_str(),
+            UNIT_CORRECTION,
+        )?;
+
         println!(
             "Min tokens to win all possible prizes with unit correction: {}",
-            ClawMachine::machines_from_str_with_unit_correction(
-                fs::read_to_string(path)?.as_str(),
-                UNIT_CORRECTION
-            )?
-            .iter()
-            .map(|machine| machine.min_tokens_to_win().unwrap_or(0))
-            .sum::<u64>()
+            claw_machines
+                .iter()
+                .map(|machine| machine.min_tokens_to_win().unwrap_or(0))
+                .sum::<u64>()
         );
 
+        if report {
+            print_report(&claw_machines);
+        }
+
         Ok(())
     } else {
-        Err("Usage: day13 INPUT_FILE_PATH".into())
+        Err("Usage: day13 [--press-limit N] [--no-press-limit] [--report] INPUT_FILE_PATH".into())
+    }
+}
+
+fn print_report(claw_machines: &[ClawMachine]) {
+    for (i, machine) in claw_machines.iter().enumerate() {
+        match machine.outcome() {
+            Ok(win) => println!(
+                "  Machine {}: {} for {} tokens",
+                i + 1,
+                machine
+                    .buttons
+                    .iter()
+                    .zip(&win.presses)
+                    .map(|(button, presses)| format!("{} x{}", button.label, presses))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                win.tokens
+            ),
+            Err(loss) => println!("  Machine {}: no prize ({})", i + 1, loss),
+        }
     }
 }
 
 type Vector2d = (i64, i64);
 
 struct ClawMachine {
-    buttons: [Vector2d; 2],
+    buttons: Vec<Button>,
     prize: Vector2d,
+    press_limit: Option<u64>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Button {
+    label: String,
+    movement: Vector2d,
+    tokens: u64,
 }
 
 impl ClawMachine {
@@ -55,65 +120,521 @@ impl ClawMachine {
         s.split("\n\n")
             .map(ClawMachine::from_str)
             .map(|result| {
-                result.map(|machine| ClawMachine {
-                    buttons: machine.buttons,
-                    prize: (
-                        machine.prize.0 + unit_correction,
-                        machine.prize.1 + unit_correction,
-                    ),
+                result.and_then(|machine| {
+                    Ok(ClawMachine {
+                        buttons: machine.buttons,
+                        press_limit: machine.press_limit,
+                        prize: (
+                            machine
+                                .prize
+                                .0
+                                .checked_add(unit_correction)
+                                .ok_or("Prize X overflowed with unit correction")?,
+                            machine
+                                .prize
+                                .1
+                                .checked_add(unit_correction)
+                                .ok_or("Prize Y overflowed with unit correction")?,
+                        ),
+                    })
                 })
             })
             .collect()
     }
 
+    /// Limits the number of times any one button may be pressed.
+    pub fn with_press_limit(self, press_limit: Option<u64>) -> Self {
+        ClawMachine {
+            press_limit,
+            ..self
+        }
+    }
+
     pub fn min_tokens_to_win(&self) -> Option<u64> {
-        let mut min_tokens_to_win: Option<u64> = None;
+        self.presses_to_win()?
+            .iter()
+            .zip(&self.buttons)
+            .try_fold(0u64, |tokens, (&presses, button)| {
+                tokens.checked_add(presses.checked_mul(button.tokens)?)
+            })
+    }
 
-        let (x_a, y_a) = self.buttons[0];
-        let (x_b, y_b) = self.buttons[1];
-        let (x_p, y_p) = self.prize;
+    /// Finds the (non-negative) number of times to press each button to win the prize for the
+    /// fewest tokens, if the prize can be won at all.
+    ///
+    /// With two buttons (or fewer), this solves for the presses directly. With more, it finds the
+    /// pair of buttons that would be cheapest if fractional presses were allowed, then searches
+    /// over presses of the remaining buttons and solves for that pair exactly. The search for
+    /// each remaining button is bounded both by the prize and by a proximity bound (the number of
+    /// buttons times the largest subdeterminant of the button movements); some optimal solution
+    /// always falls within the latter no matter how far away the prize is. That bound only holds
+    /// when presses are unlimited, so with a press limit, the search runs up to the limit instead.
+    ///
+    /// The search tries every combination of presses of the remaining buttons, so it takes time
+    /// exponential in the number of buttons; machines that would need more than
+    /// [`MAX_SEARCH_COMBINATIONS`] combinations aren't searched at all.
+    pub fn presses_to_win(&self) -> Option<Vec<u64>> {
+        self.presses_to_win_within(self.press_limit.map_or(i128::MAX, i128::from))
+    }
 
-        let b_presses = ((x_p * y_a) - (x_a * y_p)) / ((x_b * y_a) - (x_a * y_b));
-        let a_presses = (x_p - (b_presses * x_b)) / x_a;
+    /// Explains the outcome of playing this machine: the presses and tokens needed to win, or why
+    /// the prize can't be won.
+    pub fn outcome(&self) -> Result<Win, Loss> {
+        if let Some(presses) = self.presses_to_win() {
+            let tokens = self.min_tokens_to_win().ok_or(Loss::TooManyTokens)?;
+            return Ok(Win { presses, tokens });
+        }
 
-        if (a_presses * x_a) + (b_presses * x_b) == x_p
-            && (a_presses * y_a) + (b_presses * y_b) == y_p
+        if let Some(pair) = self
+            .cheapest_fractional_pair()?
+            .filter(|_| self.buttons.len() > 2)
         {
-            let tokens = (a_presses * BUTTON_A_TOKENS) + (b_presses * BUTTON_B_TOKENS);
+            let others: Vec<usize> = (0..self.buttons.len())
+                .filter(|&i| i != pair.0 && i != pair.1)
+                .collect();
 
-            min_tokens_to_win = min_tokens_to_win
-                .map(|t| t.min(tokens as u64))
-                .or(Some(tokens as u64))
+            let combinations = [self.press_limit.map_or(i128::MAX, i128::from), i128::MAX]
+                .into_iter()
+                .map(|press_limit| {
+                    self.search_combinations(&others, self.search_limit(press_limit))
+                })
+                .max()
+                .unwrap();
+
+            if combinations > MAX_SEARCH_COMBINATIONS {
+                return Err(Loss::SearchTooLarge { combinations });
+            }
+        }
+
+        if let Some(press_limit) = self.press_limit {
+            if let Some(presses) = self.presses_to_win_within(i128::MAX) {
+                return Err(Loss::ExceedsPressLimit {
+                    presses,
+                    press_limit,
+                });
+            }
         }
 
-        min_tokens_to_win
+        if let [a, b] = self.buttons.as_slice() {
+            let (x_a, y_a) = (a.movement.0 as i128, a.movement.1 as i128);
+            let (x_b, y_b) = (b.movement.0 as i128, b.movement.1 as i128);
+            let (x_p, y_p) = (self.prize.0 as i128, self.prize.1 as i128);
+
+            let determinant = (x_b * y_a) - (x_a * y_b);
+
+            if determinant != 0 {
+                let b_numerator = (x_p * y_a) - (x_a * y_p);
+                let a_numerator = (x_b * y_p) - (x_p * y_b);
+
+                return if b_numerator % determinant != 0 || a_numerator % determinant != 0 {
+                    Err(Loss::NonIntegerSolution)
+                } else {
+                    Err(Loss::NegativePresses {
+                        presses: vec![a_numerator / determinant, b_numerator / determinant],
+                    })
+                };
+            }
+        }
+
+        Err(Loss::NoSolution)
+    }
+
+    fn presses_to_win_within(&self, press_limit: i128) -> Option<Vec<u64>> {
+        // Work in 128 bits so unit-corrected prizes can't overflow intermediate products
+        let prize = (self.prize.0 as i128, self.prize.1 as i128);
+
+        let presses = match self.buttons.len() {
+            0 => (prize == (0, 0)).then(Vec::new)?,
+            1 => {
+                let no_op = Button {
+                    label: String::new(),
+                    movement: (0, 0),
+                    tokens: 0,
+                };
+
+                vec![Self::pair_presses(&self.buttons[0], &no_op, prize, press_limit)?[0]]
+            }
+            2 => {
+                Self::pair_presses(&self.buttons[0], &self.buttons[1], prize, press_limit)?.to_vec()
+            }
+            _ => {
+                let pair = self.cheapest_fractional_pair().ok()??;
+                let others: Vec<usize> = (0..self.buttons.len())
+                    .filter(|&i| i != pair.0 && i != pair.1)
+                    .collect();
+
+                let search_limit = self.search_limit(press_limit);
+
+                if self.search_combinations(&others, search_limit) > MAX_SEARCH_COMBINATIONS {
+                    return None;
+                }
+
+                let mut presses = vec![0; self.buttons.len()];
+                let mut best = None;
+
+                self.search_presses(
+                    &others,
+                    pair,
+                    (search_limit, press_limit),
+                    prize,
+                    &mut presses,
+                    &mut best,
+                );
+
+                best.map(|(_, presses)| presses)?
+            }
+        };
+
+        presses
+            .into_iter()
+            .map(|presses| u64::try_from(presses).ok())
+            .collect()
     }
 
-    fn button_from_str(s: &str) -> Result<Vector2d, Box<dyn Error>> {
-        if !s.starts_with("Button ") {
-            return Err("Button line must start with 'Button: '".into());
+    /// Tries every number of presses of the `others` buttons up to the first limit, then solves
+    /// for the `pair` of buttons, which may be pressed up to the second limit.
+    fn search_presses(
+        &self,
+        others: &[usize],
+        pair: (usize, usize),
+        (search_limit, press_limit): (i128, i128),
+        remaining: (i128, i128),
+        presses: &mut Vec<i128>,
+        best: &mut Option<(i128, Vec<i128>)>,
+    ) {
+        if let Some((&i, others)) = others.split_first() {
+            let (x, y) = self.buttons[i].movement;
+            let (x, y) = (x as i128, y as i128);
+
+            for count in 0..=self.max_presses(i, remaining, search_limit) {
+                presses[i] = count;
+
+                self.search_presses(
+                    others,
+                    pair,
+                    (search_limit, press_limit),
+                    (remaining.0 - count * x, remaining.1 - count * y),
+                    presses,
+                    best,
+                );
+            }
+
+            presses[i] = 0;
+        } else if let Some([a_presses, b_presses]) = Self::pair_presses(
+            &self.buttons[pair.0],
+            &self.buttons[pair.1],
+            remaining,
+            press_limit,
+        ) {
+            presses[pair.0] = a_presses;
+            presses[pair.1] = b_presses;
+
+            // A combination whose token count doesn't even fit in 128 bits can't be the cheapest
+            // one that's worth reporting, so skip it
+            let tokens =
+                presses
+                    .iter()
+                    .zip(&self.buttons)
+                    .try_fold(0i128, |tokens, (&presses, button)| {
+                        tokens.checked_add(presses.checked_mul(button.tokens as i128)?)
+                    });
+
+            if let Some(tokens) = tokens.filter(|&tokens| {
+                best.as_ref()
+                    .is_none_or(|&(best_tokens, _)| tokens < best_tokens)
+            }) {
+                *best = Some((tokens, presses.clone()));
+            }
+
+            presses[pair.0] = 0;
+            presses[pair.1] = 0;
         }
+    }
 
-        // Button A: X+12, Y+25
-        if let Some(label_end) = s.find(": ") {
-            if let Some((x, y)) = s[label_end + 2..].split_once(", ") {
-                let x = x
-                    .strip_prefix("X+")
-                    .ok_or("Could not parse X component")?
-                    .parse()?;
+    /// The most presses of any button outside the pair to search: the proximity bound if presses
+    /// are unlimited, or the press limit otherwise.
+    fn search_limit(&self, press_limit: i128) -> i128 {
+        if press_limit == i128::MAX {
+            self.buttons.len() as i128 * self.max_subdeterminant()
+        } else {
+            press_limit
+        }
+    }
 
-                let y = y
-                    .strip_prefix("Y+")
-                    .ok_or("Could not parse Y component")?
-                    .parse()?;
+    /// Counts the combinations of presses of the `others` buttons that a search would try.
+    fn search_combinations(&self, others: &[usize], search_limit: i128) -> u128 {
+        let prize = (self.prize.0 as i128, self.prize.1 as i128);
 
-                Ok((x, y))
+        others.iter().fold(1, |combinations, &i| {
+            combinations.saturating_mul(self.max_presses(i, prize, search_limit) as u128 + 1)
+        })
+    }
+
+    /// Finds the most times button `i` can be pressed without exceeding `search_limit` or passing
+    /// the `remaining` distance to the prize on either axis.
+    fn max_presses(&self, i: usize, remaining: (i128, i128), search_limit: i128) -> i128 {
+        let (x, y) = self.buttons[i].movement;
+
+        [(x as i128, remaining.0), (y as i128, remaining.1)]
+            .into_iter()
+            .filter(|&(component, _)| component > 0)
+            .map(|(component, remaining)| remaining / component)
+            .min()
+            .unwrap_or(0)
+            .min(search_limit)
+    }
+
+    /// Finds the pair of buttons that wins the prize for the fewest tokens if buttons could be
+    /// pressed a fractional number of times, or `None` if the prize can't be won even then. Fails
+    /// if some pair's cost doesn't fit in 128 bits, since the pairs then can't be compared.
+    fn cheapest_fractional_pair(&self) -> Result<Option<(usize, usize)>, Loss> {
+        let (x_p, y_p) = (self.prize.0 as i128, self.prize.1 as i128);
+
+        // Tokens as a fraction (numerator, positive denominator)
+        let mut cheapest: Option<((i128, i128), (usize, usize))> = None;
+        let mut independent = false;
+
+        for i in 0..self.buttons.len() {
+            for j in i + 1..self.buttons.len() {
+                let (x_a, y_a) = self.buttons[i].movement;
+                let (x_b, y_b) = self.buttons[j].movement;
+                let (x_a, y_a, x_b, y_b) = (x_a as i128, y_a as i128, x_b as i128, y_b as i128);
+
+                let determinant = (x_b * y_a) - (x_a * y_b);
+
+                if determinant == 0 {
+                    continue;
+                }
+
+                independent = true;
+
+                let sign = determinant.signum();
+                let a_numerator = ((x_b * y_p) - (x_p * y_b)) * sign;
+                let b_numerator = ((x_p * y_a) - (x_a * y_p)) * sign;
+
+                if a_numerator < 0 || b_numerator < 0 {
+                    continue;
+                }
+
+                let tokens = (
+                    a_numerator
+                        .checked_mul(self.buttons[i].tokens as i128)
+                        .and_then(|a_tokens| {
+                            a_tokens.checked_add(
+                                b_numerator.checked_mul(self.buttons[j].tokens as i128)?,
+                            )
+                        })
+                        .ok_or(Loss::Overflow)?,
+                    determinant.abs(),
+                );
+
+                if cheapest
+                    .is_none_or(|(cheapest_tokens, _)| fraction_less_than(tokens, cheapest_tokens))
+                {
+                    cheapest = Some((tokens, (i, j)));
+                }
+            }
+        }
+
+        if independent {
+            return Ok(cheapest.map(|(_, pair)| pair));
+        }
+
+        // Every button moves along the same line, so the cheapest button per unit of distance
+        // along that line is best; pair it with any other button
+        let axis = |movement: Vector2d| {
+            if self.buttons.iter().any(|button| button.movement.0 != 0) {
+                movement.0 as i128
             } else {
-                Err("Could not split x/y components".into())
+                movement.1 as i128
+            }
+        };
+
+        let cheapest = (0..self.buttons.len())
+            .filter(|&i| axis(self.buttons[i].movement) > 0)
+            .min_by(|&i, &j| {
+                let (tokens_i, tokens_j) = (self.buttons[i].tokens, self.buttons[j].tokens);
+
+                (tokens_i as i128 * axis(self.buttons[j].movement))
+                    .cmp(&(tokens_j as i128 * axis(self.buttons[i].movement)))
+            })
+            .unwrap_or(0);
+
+        Ok(Some((cheapest, if cheapest == 0 { 1 } else { 0 })))
+    }
+
+    /// Finds the largest absolute value of any 1x1 or 2x2 subdeterminant of the matrix whose
+    /// columns are the button movements.
+    fn max_subdeterminant(&self) -> i128 {
+        let movements: Vec<(i128, i128)> = self
+            .buttons
+            .iter()
+            .map(|button| (button.movement.0 as i128, button.movement.1 as i128))
+            .collect();
+
+        let max_entry = movements
+            .iter()
+            .map(|&(x, y)| x.abs().max(y.abs()))
+            .max()
+            .unwrap_or(0);
+
+        let max_determinant = movements
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| movements[i + 1..].iter().map(move |b| (a, b)))
+            .map(|(&(x_a, y_a), &(x_b, y_b))| ((x_b * y_a) - (x_a * y_b)).abs())
+            .max()
+            .unwrap_or(0);
+
+        max_entry.max(max_determinant).max(1)
+    }
+
+    /// Finds the cheapest non-negative number of presses of two buttons, neither pressed more than
+    /// `press_limit` times, to reach the given position exactly.
+    fn pair_presses(
+        a: &Button,
+        b: &Button,
+        prize: (i128, i128),
+        press_limit: i128,
+    ) -> Option<[i128; 2]> {
+        let (x_a, y_a) = (a.movement.0 as i128, a.movement.1 as i128);
+        let (x_b, y_b) = (b.movement.0 as i128, b.movement.1 as i128);
+        let (x_p, y_p) = prize;
+
+        let determinant = (x_b * y_a) - (x_a * y_b);
+
+        let (a_presses, b_presses) = if determinant != 0 {
+            // The buttons are linearly independent, so there's exactly one (rational) solution
+            let b_numerator = (x_p * y_a) - (x_a * y_p);
+            let a_numerator = (x_b * y_p) - (x_p * y_b);
+
+            if b_numerator % determinant != 0 || a_numerator % determinant != 0 {
+                return None;
+            }
+
+            (a_numerator / determinant, b_numerator / determinant)
+        } else {
+            // The buttons move the claw along the same line (or not at all), so solve along
+            // whichever axis the buttons actually move, then make sure that lands on the prize
+            let (u, v, w) = if x_a != 0 || x_b != 0 {
+                (x_a, x_b, x_p)
+            } else {
+                (y_a, y_b, y_p)
+            };
+
+            let (a_presses, b_presses) = Self::min_cost_presses_1d(
+                u,
+                v,
+                w,
+                a.tokens as i128,
+                b.tokens as i128,
+                press_limit,
+            )?;
+
+            if (a_presses * x_a) + (b_presses * x_b) != x_p
+                || (a_presses * y_a) + (b_presses * y_b) != y_p
+            {
+                return None;
+            }
+
+            (a_presses, b_presses)
+        };
+
+        if a_presses < 0 || b_presses < 0 || a_presses > press_limit || b_presses > press_limit {
+            return None;
+        }
+
+        Some([a_presses, b_presses])
+    }
+
+    /// Minimizes `a * a_cost + b * b_cost` over integers in `0..=press_limit` with
+    /// `a * u + b * v = w`, where `u` and `v` are non-negative.
+    fn min_cost_presses_1d(
+        u: i128,
+        v: i128,
+        w: i128,
+        a_cost: i128,
+        b_cost: i128,
+        press_limit: i128,
+    ) -> Option<(i128, i128)> {
+        match (u, v) {
+            (0, 0) => (w == 0).then_some((0, 0)),
+            (0, _) => (w % v == 0).then_some((0, w / v)),
+            (_, 0) => (w % u == 0).then_some((w / u, 0)),
+            _ => {
+                let (g, x, y) = extended_gcd(u, v);
+
+                if w % g != 0 {
+                    return None;
+                }
+
+                // Every solution has the form a = a0 + t * (v / g), b = b0 - t * (u / g)
+                let (a0, b0) = (x * (w / g), y * (w / g));
+                let (a_step, b_step) = (v / g, u / g);
+
+                // Keep both a and b in 0..=press_limit
+                let t_min =
+                    ceil_div(-a0, a_step).max(ceil_div(b0.saturating_sub(press_limit), b_step));
+                let t_max = b0
+                    .div_euclid(b_step)
+                    .min(press_limit.saturating_sub(a0).div_euclid(a_step));
+
+                if t_min > t_max {
+                    return None;
+                }
+
+                // Cost is linear in t, so the cheapest solution is at one end of the range
+                let t = if a_cost * a_step < b_cost * b_step {
+                    t_max
+                } else {
+                    t_min
+                };
+
+                Some((a0 + t * a_step, b0 - t * b_step))
             }
+        }
+    }
+
+    fn button_from_str(s: &str) -> Result<Button, Box<dyn Error>> {
+        // Button A: X+12, Y+25[, Cost=3]
+        let s = s
+            .strip_prefix("Button ")
+            .ok_or("Button line must start with 'Button '")?;
+
+        let (label, components) = s.split_once(": ").ok_or("Could not find label end")?;
+        let mut components = components.split(", ");
+
+        let x = components
+            .next()
+            .and_then(|x| x.strip_prefix("X+"))
+            .ok_or("Could not parse X component")?
+            .parse::<u32>()?;
+
+        let y = components
+            .next()
+            .and_then(|y| y.strip_prefix("Y+"))
+            .ok_or("Could not parse Y component")?
+            .parse::<u32>()?;
+
+        let tokens = if let Some(cost) = components.next() {
+            cost.strip_prefix("Cost=")
+                .ok_or("Could not parse button cost")?
+                .parse()?
+        } else if label == "A" {
+            BUTTON_A_TOKENS
         } else {
-            Err("Could not find label end".into())
+            BUTTON_TOKENS
+        };
+
+        if components.next().is_some() {
+            return Err("Unexpected button component".into());
         }
+
+        Ok(Button {
+            label: String::from(label),
+            movement: (x as i64, y as i64),
+            tokens,
+        })
     }
 
     fn prize_from_str(s: &str) -> Result<Vector2d, Box<dyn Error>> {
@@ -139,18 +660,118 @@ impl ClawMachine {
     }
 }
 
+/// Compares two non-negative fractions (numerator, positive denominator) without multiplying, by
+/// comparing their continued fraction expansions term by term.
+fn fraction_less_than(a: (i128, i128), b: (i128, i128)) -> bool {
+    let (mut a, mut b) = (a, b);
+
+    loop {
+        let (a_whole, a_remainder) = (a.0 / a.1, a.0 % a.1);
+        let (b_whole, b_remainder) = (b.0 / b.1, b.0 % b.1);
+
+        if a_whole != b_whole {
+            return a_whole < b_whole;
+        } else if b_remainder == 0 {
+            return false;
+        } else if a_remainder == 0 {
+            return true;
+        }
+
+        // With equal whole parts, a < b exactly when the reciprocal of b's remainder is less than
+        // the reciprocal of a's
+        (a, b) = ((b.1, b_remainder), (a.1, a_remainder));
+    }
+}
+
+/// Divides `n` by a positive `d`, rounding toward positive infinity.
+fn ceil_div(n: i128, d: i128) -> i128 {
+    n.div_euclid(d) + i128::from(n.rem_euclid(d) != 0)
+}
+
+/// Returns `(g, x, y)` such that `g = gcd(a, b) = a * x + b * y`.
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+struct Win {
+    presses: Vec<u64>,
+    tokens: u64,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Loss {
+    /// The only way to reach the prize takes a fractional number of presses
+    NonIntegerSolution,
+    /// The only way to reach the prize takes a negative number of presses of some button
+    NegativePresses { presses: Vec<i128> },
+    /// The prize can be won, but only by pressing some button too many times
+    ExceedsPressLimit { presses: Vec<u64>, press_limit: u64 },
+    /// The prize can be won, but the token count doesn't fit in 64 bits
+    TooManyTokens,
+    /// The numbers involved in finding the cheapest presses don't fit in 128 bits
+    Overflow,
+    /// Too many combinations of button presses would need to be searched to find a solution
+    SearchTooLarge { combinations: u128 },
+    /// No combination of button presses reaches the prize
+    NoSolution,
+}
+
+impl Display for Loss {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Loss::NonIntegerSolution => write!(f, "non-integer solution"),
+            Loss::NegativePresses { presses } => write!(
+                f,
+                "negative presses ({})",
+                presses
+                    .iter()
+                    .map(|presses| presses.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Loss::ExceedsPressLimit {
+                presses,
+                press_limit,
+            } => write!(
+                f,
+                "exceeds the {}-press limit ({})",
+                press_limit,
+                presses
+                    .iter()
+                    .map(|presses| presses.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Loss::TooManyTokens => write!(f, "too many tokens"),
+            Loss::Overflow => write!(f, "numbers too large to solve"),
+            Loss::SearchTooLarge { combinations } => write!(
+                f,
+                "too many combinations of presses to search ({})",
+                combinations
+            ),
+            Loss::NoSolution => write!(f, "no combination of presses reaches the prize"),
+        }
+    }
+}
+
 impl FromStr for ClawMachine {
     type Err = Box<dyn Error>;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let &[button_a, button_b, prize] = s.lines().collect::<Vec<&str>>().as_slice() {
+        if let Some((prize, buttons)) = s.lines().collect::<Vec<&str>>().split_last() {
             Ok(Self {
-                buttons: [
-                    Self::button_from_str(button_a)?,
-                    Self::button_from_str(button_b)?,
-                ],
+                buttons: buttons
+                    .iter()
+                    .map(|button| Self::button_from_str(button))
+                    .collect::<Result<_, _>>()?,
 
                 prize: Self::prize_from_str(prize)?,
+                press_limit: None,
             })
         } else {
             Err("Could not parse machine string".into())
@@ -202,4 +823,301 @@ mod test {
         assert!(claw_machines[2].min_tokens_to_win().is_none());
         assert!(claw_machines[3].min_tokens_to_win().is_some());
     }
+
+    #[test]
+    fn test_min_tokens_to_win_degenerate() {
+        let machine = |[a, b]: [Vector2d; 2], prize: Vector2d| ClawMachine {
+            buttons: vec![
+                button("A", a, BUTTON_A_TOKENS),
+                button("B", b, BUTTON_TOKENS),
+            ],
+            prize,
+            press_limit: None,
+        };
+
+        // Collinear buttons
+        assert_eq!(
+            Some(5),
+            machine([(1, 1), (2, 2)], (10, 10)).min_tokens_to_win()
+        );
+        assert_eq!(
+            Some(5),
+            machine([(3, 3), (2, 2)], (7, 7)).min_tokens_to_win()
+        );
+        assert_eq!(
+            Some(6),
+            machine([(3, 3), (2, 2)], (9, 9)).min_tokens_to_win()
+        );
+        assert_eq!(None, machine([(3, 3), (2, 2)], (7, 8)).min_tokens_to_win());
+        assert_eq!(None, machine([(4, 4), (2, 2)], (7, 7)).min_tokens_to_win());
+        assert_eq!(
+            Some(3),
+            machine([(0, 5), (0, 0)], (0, 5)).min_tokens_to_win()
+        );
+
+        // Buttons that don't move the claw at all
+        assert_eq!(
+            Some(0),
+            machine([(0, 0), (0, 0)], (0, 0)).min_tokens_to_win()
+        );
+        assert_eq!(None, machine([(0, 0), (0, 0)], (1, 0)).min_tokens_to_win());
+
+        // A zero X component
+        assert_eq!(
+            Some(5),
+            machine([(0, 5), (2, 3)], (4, 11)).min_tokens_to_win()
+        );
+
+        // A solution with negative presses
+        assert_eq!(None, machine([(1, 2), (2, 1)], (0, 3)).min_tokens_to_win());
+        assert_eq!(
+            Some(vec![2, 0]),
+            machine([(1, 2), (2, 1)], (2, 4)).presses_to_win()
+        );
+    }
+
+    #[test]
+    fn test_presses_to_win_n_buttons() {
+        let claw_machines = ClawMachine::machines_from_str(indoc! {"
+            Button A: X+1, Y+0, Cost=1
+            Button B: X+0, Y+1, Cost=1
+            Button C: X+1, Y+1, Cost=1
+            Prize: X=5, Y=3
+
+            Button A: X+2, Y+2
+            Button B: X+3, Y+3
+            Button C: X+5, Y+5
+            Prize: X=7, Y=7
+
+            Button A: X+4, Y+1
+            Prize: X=12, Y=3
+
+            Button A: X+3, Y+7, Cost=2
+            Button B: X+7, Y+2, Cost=2
+            Button C: X+1, Y+1, Cost=3
+            Button D: X+9, Y+9, Cost=20
+            Prize: X=30, Y=30
+        "})
+        .unwrap();
+
+        assert_eq!(Some(vec![2, 0, 3]), claw_machines[0].presses_to_win());
+        assert_eq!(Some(5), claw_machines[0].min_tokens_to_win());
+
+        assert_eq!(Some(vec![1, 0, 1]), claw_machines[1].presses_to_win());
+        assert_eq!(Some(4), claw_machines[1].min_tokens_to_win());
+
+        assert_eq!(Some(vec![3]), claw_machines[2].presses_to_win());
+
+        // Compare against an exhaustive search
+        let expected = (0..=10u64)
+            .flat_map(|a| (0..=10u64).map(move |b| (a, b)))
+            .flat_map(|(a, b)| (0..=30u64).map(move |c| (a, b, c)))
+            .flat_map(|(a, b, c)| (0..=3u64).map(move |d| [a, b, c, d]))
+            .filter(|p| {
+                3 * p[0] + 7 * p[1] + p[2] + 9 * p[3] == 30
+                    && 7 * p[0] + 2 * p[1] + p[2] + 9 * p[3] == 30
+            })
+            .map(|p| 2 * p[0] + 2 * p[1] + 3 * p[2] + 20 * p[3])
+            .min();
+
+        assert_eq!(expected, claw_machines[3].min_tokens_to_win());
+
+        // The cheapest pair (A and B) can only reach the prize with help from C
+        let machine = ClawMachine::from_str(indoc! {"
+            Button A: X+3, Y+0, Cost=1
+            Button B: X+0, Y+3, Cost=1
+            Button C: X+1, Y+1, Cost=5
+            Prize: X=4, Y=4
+        "})
+        .unwrap();
+
+        assert_eq!(Ok(Some((0, 1))), machine.cheapest_fractional_pair());
+        assert_eq!(Some(vec![1, 1, 1]), machine.presses_to_win());
+        assert_eq!(Some(7), machine.min_tokens_to_win());
+
+        // A huge prize is still quick to win
+        let claw_machine = ClawMachine {
+            buttons: claw_machines[0].buttons.clone(),
+            prize: (UNIT_CORRECTION + 5, UNIT_CORRECTION + 3),
+            press_limit: None,
+        };
+
+        assert_eq!(
+            Some(vec![2, 0, UNIT_CORRECTION as u64 + 3]),
+            claw_machine.presses_to_win()
+        );
+    }
+
+    #[test]
+    fn test_button_from_str() {
+        assert_eq!(
+            button("A", (94, 34), 3),
+            ClawMachine::button_from_str("Button A: X+94, Y+34").unwrap()
+        );
+
+        assert_eq!(
+            button("C", (1, 2), 7),
+            ClawMachine::button_from_str("Button C: X+1, Y+2, Cost=7").unwrap()
+        );
+
+        assert!(ClawMachine::button_from_str("Button A: X+-1, Y+2").is_err());
+        assert!(ClawMachine::button_from_str("Button A: X+1, Y+2, Tokens=7").is_err());
+    }
+
+    fn button(label: &str, movement: Vector2d, tokens: u64) -> Button {
+        Button {
+            label: String::from(label),
+            movement,
+            tokens,
+        }
+    }
+
+    #[test]
+    fn test_outcome() {
+        let claw_machines: Vec<ClawMachine> = ClawMachine::machines_from_str(TEST_MACHINES)
+            .unwrap()
+            .into_iter()
+            .map(|machine| machine.with_press_limit(Some(85)))
+            .collect();
+
+        let win = claw_machines[0].outcome().unwrap();
+        assert_eq!(vec![80, 40], win.presses);
+        assert_eq!(280, win.tokens);
+
+        assert_eq!(
+            Err(Loss::NonIntegerSolution),
+            claw_machines[1].outcome().map(|win| win.presses)
+        );
+
+        assert_eq!(
+            Err(Loss::ExceedsPressLimit {
+                presses: vec![38, 86],
+                press_limit: 85
+            }),
+            claw_machines[2].outcome().map(|win| win.presses)
+        );
+
+        let machine = ClawMachine::from_str(indoc! {"
+            Button A: X+1, Y+2
+            Button B: X+2, Y+1
+            Prize: X=0, Y=3
+        "})
+        .unwrap();
+
+        assert_eq!(
+            Err(Loss::NegativePresses {
+                presses: vec![2, -1]
+            }),
+            machine.outcome().map(|win| win.presses)
+        );
+
+        // With collinear buttons, the limit can rule out the cheapest solution but not others
+        let machine = ClawMachine::from_str(indoc! {"
+            Button A: X+3, Y+3
+            Button B: X+1, Y+1
+            Prize: X=30, Y=30
+        "})
+        .unwrap();
+
+        assert_eq!(Some(30), machine.min_tokens_to_win());
+
+        let machine = machine.with_press_limit(Some(12));
+        assert_eq!(Some(vec![6, 12]), machine.presses_to_win());
+        assert_eq!(Some(30), machine.min_tokens_to_win());
+
+        let machine = machine.with_press_limit(Some(7));
+        assert_eq!(
+            Err(Loss::ExceedsPressLimit {
+                presses: vec![0, 30],
+                press_limit: 7
+            }),
+            machine.outcome().map(|win| win.presses)
+        );
+    }
+
+    #[test]
+    fn test_presses_to_win_n_buttons_with_press_limit() {
+        // The cheapest pair (A and B) would need 150 presses each; the limit forces at least 50
+        // presses of C, well past the proximity bound of 3 presses
+        let machine = ClawMachine::from_str(indoc! {"
+            Button A: X+1, Y+0, Cost=1
+            Button B: X+0, Y+1, Cost=1
+            Button C: X+1, Y+1, Cost=3
+            Prize: X=150, Y=150
+        "})
+        .unwrap()
+        .with_press_limit(Some(100));
+
+        assert_eq!(Some(vec![100, 100, 50]), machine.presses_to_win());
+        assert_eq!(Some(350), machine.min_tokens_to_win());
+    }
+
+    #[test]
+    fn test_search_too_large() {
+        let machine = ClawMachine::from_str(indoc! {"
+            Button A: X+1000, Y+1, Cost=1
+            Button B: X+1, Y+1000, Cost=1
+            Button C: X+999, Y+2
+            Button D: X+2, Y+999
+            Button E: X+500, Y+500
+            Prize: X=10000000000000, Y=10000000000000
+        "})
+        .unwrap();
+
+        assert_eq!(None, machine.presses_to_win());
+        assert!(matches!(
+            machine.outcome(),
+            Err(Loss::SearchTooLarge { combinations }) if combinations > MAX_SEARCH_COMBINATIONS
+        ));
+    }
+
+    #[test]
+    fn test_n_buttons_overflow() {
+        let machine = ClawMachine::from_str(indoc! {"
+            Button A: X+4000000000, Y+3
+            Button B: X+5, Y+4000000000
+            Button C: X+7, Y+9
+            Prize: X=9000000000000000000, Y=9000000000000000000
+        "})
+        .unwrap();
+
+        // Comparing the pairs' fractional costs no longer overflows, but the search is hopeless
+        assert_eq!(None, machine.presses_to_win());
+        assert!(matches!(
+            machine.outcome(),
+            Err(Loss::SearchTooLarge { .. })
+        ));
+
+        // With enormous costs, even a single pair's fractional cost doesn't fit in 128 bits
+        let machine = ClawMachine::from_str(indoc! {"
+            Button A: X+4000000000, Y+3, Cost=18000000000000000000
+            Button B: X+5, Y+4000000000, Cost=18000000000000000000
+            Button C: X+7, Y+9
+            Prize: X=9000000000000000000, Y=9000000000000000000
+        "})
+        .unwrap();
+
+        assert_eq!(None, machine.presses_to_win());
+        assert_eq!(
+            Err(Loss::Overflow),
+            machine.outcome().map(|win| win.presses)
+        );
+    }
+
+    #[test]
+    fn test_fraction_less_than() {
+        assert!(fraction_less_than((1, 3), (1, 2)));
+        assert!(!fraction_less_than((1, 2), (1, 3)));
+        assert!(!fraction_less_than((2, 4), (1, 2)));
+        assert!(fraction_less_than((7, 5), (10, 7)));
+        assert!(fraction_less_than((i128::MAX - 1, i128::MAX), (1, 1)));
+        assert!(!fraction_less_than((i128::MAX, 3), (i128::MAX - 1, 3)));
+    }
+
+    #[test]
+    fn test_unit_correction_overflow() {
+        assert!(
+            ClawMachine::machines_from_str_with_unit_correction(TEST_MACHINES, i64::MAX).is_err()
+        );
+    }
 }
@@ -1,31 +1,130 @@
-use std::env;
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::{env, fs};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut path = None;
+    let mut width = None;
+    let mut height = None;
+    let mut seconds = 100;
+    let mut render_seconds = None;
+    let mut frames_directory = None;
+    let mut image_format = ImageFormat::Pbm;
+    let mut scale = 4;
+    let mut first_second = 0;
+    let mut last_second = None;
+    let mut candidates = None;
+    let mut brute_force = false;
+    let mut metrics = Vec::new();
+    let mut top = 5;
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => width = Some(args.next().ok_or("Missing width")?.parse()?),
+            "--height" => height = Some(args.next().ok_or("Missing height")?.parse()?),
+            "--seconds" => seconds = args.next().ok_or("Missing seconds")?.parse()?,
+            "--render" => render_seconds = Some(args.next().ok_or("Missing render time")?.parse()?),
+            "--export-frames" => {
+                frames_directory =
+                    Some(PathBuf::from(args.next().ok_or("Missing frame directory")?))
+            }
+            "--image-format" => {
+                image_format = args.next().ok_or("Missing image format")?.parse()?
+            }
+            "--scale" => scale = args.next().ok_or("Missing scale")?.parse()?,
+            "--from" => first_second = args.next().ok_or("Missing first second")?.parse()?,
+            "--to" => last_second = Some(args.next().ok_or("Missing last second")?.parse()?),
+            "--brute-force" => brute_force = true,
+            "--metric" => {
+                metrics = MetricKind::list_from_str(&args.next().ok_or("Missing metric")?)?
+            }
+            "--top" => top = args.next().ok_or("Missing time count")?.parse()?,
+            "--candidates" => {
+                candidates = Some(args.next().ok_or("Missing candidate count")?.parse()?)
+            }
+            _ => path = Some(arg),
+        }
+    }
 
-    if let Some(path) = args.get(1) {
+    if let Some(path) = path {
         let robots = BufReader::new(File::open(path)?)
             .lines()
             .map_while(Result::ok)
             .map(|line| Robot::from_str(line.as_str()))
             .collect::<Result<Vec<_>, _>>()?;
 
-        let lobby = Lobby {
-            width: 101,
-            height: 103,
+        let (inferred_width, inferred_height) = Lobby::infer_dimensions(&robots);
+
+        let lobby = Lobby::new(
+            width.unwrap_or(inferred_width),
+            height.unwrap_or(inferred_height),
             robots,
+        )?;
+
+        println!("Lobby dimensions: {}x{}", lobby.width, lobby.height);
+        println!(
+            "Safety factor after {} seconds: {}",
+            seconds,
+            lobby.safety_factor(seconds)
+        );
+
+        let time_to_tree = if brute_force {
+            lobby.time_to_tree()
+        } else if let Some(time) = lobby.time_to_tree_by_crt() {
+            time
+        } else {
+            println!("Horizontal and vertical times are incompatible; falling back to brute force");
+            lobby.time_to_tree()
         };
 
-        println!("Safety factor: {}", lobby.safety_factor(100));
-        println!("Time of least randomness: {}", lobby.time_to_tree());
+        println!("Time of least randomness: {}", time_to_tree);
+
+        for metric_kind in metrics {
+            println!("Best times by {}:", metric_kind.name());
+
+            for (time, score) in lobby.best_times(metric_kind.metric().as_ref(), top) {
+                println!("  {}: {}", time, score);
+            }
+        }
+
+        if let Some(render_seconds) = render_seconds {
+            print!("{}", lobby.render(render_seconds));
+        }
+
+        if let Some(frames_directory) = frames_directory {
+            // Either the least random times or every second in the given range (by default, one
+            // full period of the lobby)
+            let times: Vec<i32> = if let Some(candidates) = candidates {
+                lobby
+                    .least_random_times(candidates)
+                    .into_iter()
+                    .map(|time| time as i32)
+                    .collect()
+            } else {
+                (first_second..=last_second.unwrap_or((lobby.width * lobby.height) as i32 - 1))
+                    .collect()
+            };
+
+            let frames = lobby.export_frames(&frames_directory, &times, image_format, scale)?;
+
+            println!("Wrote {} frames to {}", frames, frames_directory.display());
+        }
 
         Ok(())
     } else {
-        Err("Usage: day13 INPUT_FILE_PATH".into())
+        Err(
+            "Usage: day14 [--width N] [--height N] [--seconds N] [--render SECONDS] \
+            [--export-frames DIR] [--image-format pbm|ppm] [--scale N] [--from SECONDS] \
+            [--to SECONDS] [--candidates N] [--brute-force] \
+            [--metric variance|entropy|neighbors|longest-run|compressed-size|all] [--top N] \
+            INPUT_FILE_PATH"
+                .into(),
+        )
     }
 }
 
@@ -39,6 +138,44 @@ struct Lobby {
 }
 
 impl Lobby {
+    pub fn new(width: usize, height: usize, robots: Vec<Robot>) -> Result<Self, Box<dyn Error>> {
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid lobby dimensions: {}x{}", width, height).into());
+        }
+
+        for (i, robot) in robots.iter().enumerate() {
+            let (x, y) = robot.initial_position;
+
+            if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+                return Err(format!(
+                    "Robot {} starts at ({}, {}), outside the {}x{} lobby",
+                    i + 1,
+                    x,
+                    y,
+                    width,
+                    height
+                )
+                .into());
+            }
+        }
+
+        Ok(Lobby {
+            width,
+            height,
+            robots,
+        })
+    }
+
+    /// Infers the smallest lobby dimensions that contain every robot's starting position.
+    pub fn infer_dimensions(robots: &[Robot]) -> (usize, usize) {
+        robots.iter().fold((0, 0), |(width, height), robot| {
+            (
+                width.max(robot.initial_position.0.max(0) as usize + 1),
+                height.max(robot.initial_position.1.max(0) as usize + 1),
+            )
+        })
+    }
+
     pub fn safety_factor(&self, seconds: i32) -> u32 {
         let mut quadrants = [0; 4];
 
@@ -77,30 +214,7 @@ impl Lobby {
         let mut min_randomness_time = 0;
 
         for time in 0..=time_max {
-            let positions: Vec<Vector2d> = self
-                .robots
-                .iter()
-                .map(|robot| robot.position_after_seconds(time as i32, self.width, self.height))
-                .collect();
-
-            let (x_mean, y_mean) = positions
-                .iter()
-                .copied()
-                .reduce(|a, b| (a.0 + b.0, a.1 + b.1))
-                .map(|(x_sum, y_sum)| {
-                    (
-                        x_sum / positions.len() as i32,
-                        y_sum / positions.len() as i32,
-                    )
-                })
-                .unwrap();
-
-            let randomness = positions
-                .iter()
-                .map(|(x, y)| ((x - x_mean).pow(2), (y - y_mean).pow(2)))
-                .reduce(|a, b| (a.0 + b.0, a.1 + b.1))
-                .map(|(x, y)| x as i64 * y as i64)
-                .unwrap();
+            let randomness = self.randomness(time as i32);
 
             if randomness < min_randomness {
                 min_randomness = randomness;
@@ -110,6 +224,409 @@ impl Lobby {
 
         min_randomness_time as u32
     }
+
+    /// Lists the `n` times within one period of the lobby with the least randomness, least random
+    /// first.
+    pub fn least_random_times(&self, n: usize) -> Vec<u32> {
+        self.best_times(&Variance, n)
+            .into_iter()
+            .map(|(time, _)| time)
+            .collect()
+    }
+
+    /// Lists the `n` times within one period of the lobby with the lowest (most orderly) scores
+    /// under the given metric, along with their scores, best first.
+    pub fn best_times(&self, metric: &dyn OrderMetric, n: usize) -> Vec<(u32, f64)> {
+        let mut times: Vec<(u32, f64)> = (0..(self.width * self.height) as u32)
+            .map(|time| (time, metric.score(self, time as i32)))
+            .collect();
+
+        times.sort_by(|(a_time, a_score), (b_time, b_score)| {
+            a_score.total_cmp(b_score).then(a_time.cmp(b_time))
+        });
+
+        times.truncate(n);
+        times
+    }
+
+    /// Finds the time of least randomness by minimizing the horizontal spread over one period of
+    /// `width` seconds and the vertical spread over one period of `height` seconds independently,
+    /// then combining the two with the Chinese remainder theorem. Returns `None` if the two times
+    /// can't both be met, which can only happen if the dimensions share a common factor.
+    pub fn time_to_tree_by_crt(&self) -> Option<u32> {
+        let least_spread_time = |period: usize, axis: fn((i64, i64)) -> i64| -> i64 {
+            (0..period as i32)
+                .min_by_key(|&time| axis(self.spreads(time)))
+                .unwrap_or(0) as i64
+        };
+
+        let x_time = least_spread_time(self.width, |(x, _)| x);
+        let y_time = least_spread_time(self.height, |(_, y)| y);
+
+        chinese_remainder(x_time, self.width as i64, y_time, self.height as i64)
+            .map(|time| time as u32)
+    }
+
+    fn randomness(&self, seconds: i32) -> i64 {
+        let (x_spread, y_spread) = self.spreads(seconds);
+        x_spread * y_spread
+    }
+
+    /// Sums the squared distances of robots from their (truncated) mean position along each axis.
+    fn spreads(&self, seconds: i32) -> (i64, i64) {
+        let positions = self.positions(seconds);
+
+        let (x_mean, y_mean) = positions
+            .iter()
+            .copied()
+            .reduce(|a, b| (a.0 + b.0, a.1 + b.1))
+            .map(|(x_sum, y_sum)| {
+                (
+                    x_sum / positions.len() as i32,
+                    y_sum / positions.len() as i32,
+                )
+            })
+            .unwrap_or_default();
+
+        positions
+            .iter()
+            .map(|(x, y)| ((x - x_mean).pow(2), (y - y_mean).pow(2)))
+            .reduce(|a, b| (a.0 + b.0, a.1 + b.1))
+            .map(|(x, y)| (x as i64, y as i64))
+            .unwrap_or_default()
+    }
+
+    fn positions(&self, seconds: i32) -> Vec<Vector2d> {
+        self.robots
+            .iter()
+            .map(|robot| robot.position_after_seconds(seconds, self.width, self.height))
+            .collect()
+    }
+
+    /// Counts the robots on each tile of the lobby after the given number of seconds, row by row.
+    fn counts(&self, seconds: i32) -> Vec<u32> {
+        let mut counts = vec![0; self.width * self.height];
+
+        for (x, y) in self.positions(seconds) {
+            counts[y as usize * self.width + x as usize] += 1;
+        }
+
+        counts
+    }
+
+    /// Draws the number of robots on each tile after the given number of seconds, with `.` for
+    /// empty tiles and `+` for tiles with more than nine robots.
+    pub fn render(&self, seconds: i32) -> String {
+        let mut rendered = String::with_capacity((self.width + 1) * self.height);
+
+        for row in self.counts(seconds).chunks(self.width) {
+            rendered.extend(row.iter().map(|&count| match count {
+                0 => '.',
+                1..=9 => char::from_digit(count, 10).unwrap(),
+                _ => '+',
+            }));
+
+            rendered.push('\n');
+        }
+
+        rendered
+    }
+
+    /// Writes the lobby after the given number of seconds as a binary PBM or PPM image, with each
+    /// tile drawn as a `scale`-by-`scale` square.
+    pub fn write_image(
+        &self,
+        seconds: i32,
+        format: ImageFormat,
+        scale: usize,
+        writer: &mut impl Write,
+    ) -> Result<(), Box<dyn Error>> {
+        let (image_width, image_height) = (self.width * scale, self.height * scale);
+
+        match format {
+            ImageFormat::Pbm => write!(writer, "P4\n{} {}\n", image_width, image_height)?,
+            ImageFormat::Ppm => write!(writer, "P6\n{} {}\n255\n", image_width, image_height)?,
+        }
+
+        for row in self.counts(seconds).chunks(self.width) {
+            let pixels: Vec<u8> = match format {
+                ImageFormat::Pbm => {
+                    // One bit per pixel (set for robots), with rows padded to a whole byte
+                    let mut pixels = vec![0; image_width.div_ceil(8)];
+
+                    for (x, _) in row
+                        .iter()
+                        .flat_map(|&count| std::iter::repeat_n(count, scale))
+                        .enumerate()
+                        .filter(|&(_, count)| count > 0)
+                    {
+                        pixels[x / 8] |= 0x80 >> (x % 8);
+                    }
+
+                    pixels
+                }
+                ImageFormat::Ppm => row
+                    .iter()
+                    .flat_map(|&count| Self::color(count).repeat(scale))
+                    .collect(),
+            };
+
+            for _ in 0..scale {
+                writer.write_all(&pixels)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes one image per given time to `directory`, named after the time, and returns the
+    /// number of images written.
+    pub fn export_frames(
+        &self,
+        directory: &Path,
+        times: &[i32],
+        format: ImageFormat,
+        scale: usize,
+    ) -> Result<usize, Box<dyn Error>> {
+        fs::create_dir_all(directory)?;
+
+        for &time in times {
+            let extension = match format {
+                ImageFormat::Pbm => "pbm",
+                ImageFormat::Ppm => "ppm",
+            };
+
+            let mut writer = BufWriter::new(File::create(
+                directory.join(format!("{:05}.{}", time, extension)),
+            )?);
+
+            self.write_image(time, format, scale, &mut writer)?;
+            writer.flush()?;
+        }
+
+        Ok(times.len())
+    }
+
+    fn color(count: u32) -> [u8; 3] {
+        match count {
+            0 => [0, 0, 0],
+            1 => [0, 160, 0],
+            2 => [0, 224, 0],
+            _ => [255, 255, 255],
+        }
+    }
+}
+
+/// A way of scoring how orderly the robots in a lobby look at a given time, where lower scores
+/// are more orderly.
+trait OrderMetric {
+    fn score(&self, lobby: &Lobby, seconds: i32) -> f64;
+}
+
+/// The product of the horizontal and vertical spreads of robots about their mean position
+struct Variance;
+
+impl OrderMetric for Variance {
+    fn score(&self, lobby: &Lobby, seconds: i32) -> f64 {
+        lobby.randomness(seconds) as f64
+    }
+}
+
+/// The Shannon entropy (in bits) of robot counts in `cell_size`-by-`cell_size` blocks of tiles
+struct Entropy {
+    cell_size: usize,
+}
+
+impl OrderMetric for Entropy {
+    fn score(&self, lobby: &Lobby, seconds: i32) -> f64 {
+        let cells_per_row = lobby.width.div_ceil(self.cell_size);
+        let mut histogram = vec![0; cells_per_row * lobby.height.div_ceil(self.cell_size)];
+
+        for (x, y) in lobby.positions(seconds) {
+            histogram
+                [(y as usize / self.cell_size) * cells_per_row + x as usize / self.cell_size] += 1;
+        }
+
+        let robots = lobby.robots.len() as f64;
+
+        histogram
+            .into_iter()
+            .filter(|&count| count > 0)
+            .map(|count| {
+                let p = count as f64 / robots;
+                -p * p.log2()
+            })
+            .sum()
+    }
+}
+
+/// The number of robots with at least one other robot on an orthogonally adjacent tile, negated
+struct NeighborCount;
+
+impl OrderMetric for NeighborCount {
+    fn score(&self, lobby: &Lobby, seconds: i32) -> f64 {
+        let counts = lobby.counts(seconds);
+        let (width, height) = (lobby.width as i32, lobby.height as i32);
+
+        let occupied = |x: i32, y: i32| {
+            x >= 0 && y >= 0 && x < width && y < height && counts[(y * width + x) as usize] > 0
+        };
+
+        let with_neighbor = lobby
+            .positions(seconds)
+            .into_iter()
+            .filter(|&(x, y)| {
+                occupied(x - 1, y) || occupied(x + 1, y) || occupied(x, y - 1) || occupied(x, y + 1)
+            })
+            .count();
+
+        -(with_neighbor as f64)
+    }
+}
+
+/// The length of the longest horizontal run of occupied tiles, negated
+struct LongestRun;
+
+impl OrderMetric for LongestRun {
+    fn score(&self, lobby: &Lobby, seconds: i32) -> f64 {
+        let longest_run = lobby
+            .counts(seconds)
+            .chunks(lobby.width)
+            .flat_map(|row| row.split(|&count| count == 0).map(|run| run.len()))
+            .max()
+            .unwrap_or(0);
+
+        -(longest_run as f64)
+    }
+}
+
+/// The size in bytes of a run-length encoding of which tiles are occupied, with each run length
+/// written as a variable-length integer; orderly pictures have fewer, longer runs.
+struct CompressedSize;
+
+impl OrderMetric for CompressedSize {
+    fn score(&self, lobby: &Lobby, seconds: i32) -> f64 {
+        let counts = lobby.counts(seconds);
+
+        let mut size = 0;
+        let mut run_start = 0;
+
+        for i in 1..=counts.len() {
+            if i == counts.len() || (counts[i] > 0) != (counts[run_start] > 0) {
+                let run_length = i - run_start;
+
+                // Seven bits per byte
+                size += (usize::BITS - run_length.leading_zeros())
+                    .div_ceil(7)
+                    .max(1);
+                run_start = i;
+            }
+        }
+
+        size as f64
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MetricKind {
+    Variance,
+    Entropy,
+    Neighbors,
+    LongestRun,
+    CompressedSize,
+}
+
+impl MetricKind {
+    const ALL: [MetricKind; 5] = [
+        MetricKind::Variance,
+        MetricKind::Entropy,
+        MetricKind::Neighbors,
+        MetricKind::LongestRun,
+        MetricKind::CompressedSize,
+    ];
+
+    /// Parses a metric name, or `all` for every metric.
+    pub fn list_from_str(s: &str) -> Result<Vec<MetricKind>, Box<dyn Error>> {
+        if s == "all" {
+            Ok(MetricKind::ALL.to_vec())
+        } else {
+            Ok(vec![MetricKind::from_str(s)?])
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MetricKind::Variance => "variance",
+            MetricKind::Entropy => "entropy",
+            MetricKind::Neighbors => "neighbors",
+            MetricKind::LongestRun => "longest-run",
+            MetricKind::CompressedSize => "compressed-size",
+        }
+    }
+
+    pub fn metric(&self) -> Box<dyn OrderMetric> {
+        match self {
+            MetricKind::Variance => Box::new(Variance),
+            MetricKind::Entropy => Box::new(Entropy { cell_size: 4 }),
+            MetricKind::Neighbors => Box::new(NeighborCount),
+            MetricKind::LongestRun => Box::new(LongestRun),
+            MetricKind::CompressedSize => Box::new(CompressedSize),
+        }
+    }
+}
+
+impl FromStr for MetricKind {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MetricKind::ALL
+            .into_iter()
+            .find(|metric_kind| metric_kind.name() == s)
+            .ok_or_else(|| format!("Unrecognized metric: {}", s).into())
+    }
+}
+
+/// Finds the smallest non-negative `t` with `t ≡ a (mod m)` and `t ≡ b (mod n)`, if any; `m` and
+/// `n` need not be coprime.
+fn chinese_remainder(a: i64, m: i64, b: i64, n: i64) -> Option<i64> {
+    let (g, m_inverse, _) = extended_gcd(m, n);
+
+    if (b - a) % g != 0 {
+        return None;
+    }
+
+    let lcm = m / g * n;
+    let k = ((b - a) / g * m_inverse).rem_euclid(n / g);
+
+    Some((a + m * k).rem_euclid(lcm))
+}
+
+/// Returns `(g, x, y)` such that `g = gcd(a, b) = a * x + b * y`.
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ImageFormat {
+    Pbm,
+    Ppm,
+}
+
+impl FromStr for ImageFormat {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pbm" => Ok(ImageFormat::Pbm),
+            "ppm" => Ok(ImageFormat::Ppm),
+            _ => Err(format!("Unrecognized image format: {}", s).into()),
+        }
+    }
 }
 
 struct Robot {
@@ -119,9 +636,11 @@ struct Robot {
 
 impl Robot {
     pub fn position_after_seconds(&self, seconds: i32, width: usize, height: usize) -> Vector2d {
+        // Positions repeat every `width` (or `height`) seconds, so reduce the time first to keep
+        // large times from overflowing
         let unwrapped = (
-            self.initial_position.0 + (self.velocity.0 * seconds),
-            self.initial_position.1 + (self.velocity.1 * seconds),
+            self.initial_position.0 + (self.velocity.0 * Self::wrap(seconds, width as i32)),
+            self.initial_position.1 + (self.velocity.1 * Self::wrap(seconds, height as i32)),
         );
 
         (
@@ -203,12 +722,205 @@ mod test {
             .collect::<Result<Vec<_>, _>>()
             .unwrap();
 
-        let lobby = Lobby {
-            width: 11,
-            height: 7,
-            robots,
-        };
+        let lobby = Lobby::new(11, 7, robots).unwrap();
 
         assert_eq!(12, lobby.safety_factor(100));
+        assert_eq!(12, lobby.safety_factor(100 + 77 * 1_000_000));
+    }
+
+    #[test]
+    fn test_lobby_dimensions() {
+        let robots = TEST_ROBOTS
+            .lines()
+            .map(Robot::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!((11, 7), Lobby::infer_dimensions(&robots));
+
+        assert!(Lobby::new(10, 7, robots).is_err());
+        assert!(Lobby::new(0, 7, vec![]).is_err());
+        assert!(Lobby::new(5, 5, vec![Robot::from_str("p=-1,0 v=1,1").unwrap()]).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let lobby = Lobby::new(11, 7, vec![Robot::from_str("p=2,4 v=2,-3").unwrap()]).unwrap();
+
+        assert_eq!(
+            indoc! {"
+                ...........
+                ...........
+                ...........
+                ...........
+                ..1........
+                ...........
+                ...........
+            "},
+            lobby.render(0)
+        );
+
+        let robots = TEST_ROBOTS
+            .lines()
+            .map(Robot::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let lobby = Lobby::new(11, 7, robots).unwrap();
+
+        assert_eq!(
+            indoc! {"
+                ......2..1.
+                ...........
+                1..........
+                .11........
+                .....1.....
+                ...12......
+                .1....1....
+            "},
+            lobby.render(100)
+        );
+    }
+
+    #[test]
+    fn test_write_image() {
+        let robots = TEST_ROBOTS
+            .lines()
+            .map(Robot::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let lobby = Lobby::new(11, 7, robots).unwrap();
+
+        let mut pbm = Vec::new();
+        lobby
+            .write_image(100, ImageFormat::Pbm, 2, &mut pbm)
+            .unwrap();
+
+        let header = b"P4\n22 14\n";
+        assert!(pbm.starts_with(header));
+        assert_eq!(header.len() + (3 * 14), pbm.len());
+
+        // The first row has robots in columns 6 and 9, so pixels 12-13 and 18-19 are set
+        assert_eq!(
+            &[0b0000_0000, 0b0000_1100, 0b0011_0000],
+            &pbm[header.len()..][..3]
+        );
+
+        let mut ppm = Vec::new();
+        lobby
+            .write_image(100, ImageFormat::Ppm, 2, &mut ppm)
+            .unwrap();
+
+        let header = b"P6\n22 14\n255\n";
+        assert!(ppm.starts_with(header));
+        assert_eq!(header.len() + (22 * 14 * 3), ppm.len());
+    }
+
+    #[test]
+    fn test_time_to_tree_by_crt() {
+        // Robots with every (non-zero) velocity that all converge on a single tile after the given
+        // number of seconds, plus a stationary one in each corner so neither axis ever has zero
+        // spread on its own
+        let converging_lobby = |width: i32, height: i32, seconds: i32| -> Lobby {
+            let mut robots: Vec<Robot> = (1..width)
+                .flat_map(|vx| (1..height).map(move |vy| (vx, vy)))
+                .map(|velocity| Robot {
+                    initial_position: (
+                        Robot::wrap(2 - velocity.0 * seconds, width),
+                        Robot::wrap(1 - velocity.1 * seconds, height),
+                    ),
+                    velocity,
+                })
+                .collect();
+
+            for initial_position in [
+                (0, 0),
+                (width - 1, 0),
+                (0, height - 1),
+                (width - 1, height - 1),
+            ] {
+                robots.push(Robot {
+                    initial_position,
+                    velocity: (0, 0),
+                });
+            }
+
+            Lobby::new(width as usize, height as usize, robots).unwrap()
+        };
+
+        let lobby = converging_lobby(11, 7, 40);
+        assert_eq!(40, lobby.time_to_tree());
+        assert_eq!(Some(40), lobby.time_to_tree_by_crt());
+
+        // 8 and 10 share a factor of 2, so the lobby repeats every 40 seconds
+        let lobby = converging_lobby(8, 10, 27);
+        assert_eq!(27, lobby.time_to_tree());
+        assert_eq!(Some(27), lobby.time_to_tree_by_crt());
+    }
+
+    #[test]
+    fn test_chinese_remainder() {
+        assert_eq!(Some(40), chinese_remainder(7, 11, 5, 7));
+        assert_eq!(Some(0), chinese_remainder(0, 11, 0, 7));
+        assert_eq!(Some(27), chinese_remainder(3, 8, 7, 10));
+        assert_eq!(None, chinese_remainder(1, 8, 2, 10));
+    }
+
+    #[test]
+    fn test_metrics() {
+        const WIDTH: i32 = 31;
+        const HEIGHT: i32 = 23;
+
+        // A simple linear congruential generator, so the "random" robots are repeatable
+        let mut state: u64 = 12345;
+        let mut random = |n: i32| -> i32 {
+            state = (state * 1103515245 + 12345) % (1 << 31);
+            ((state >> 16) % n as u64) as i32
+        };
+
+        let mut robots = Vec::new();
+
+        for _ in 0..40 {
+            robots.push(Robot {
+                initial_position: (random(WIDTH), random(HEIGHT)),
+                velocity: (random(WIDTH - 1) + 1, random(HEIGHT - 1) + 1),
+            });
+        }
+
+        // Robots that come together into a filled 8x6 rectangle after 100 seconds
+        for i in 0..48 {
+            let velocity = (random(WIDTH - 1) + 1, random(HEIGHT - 1) + 1);
+
+            robots.push(Robot {
+                initial_position: (
+                    Robot::wrap(10 + i % 8 - velocity.0 * 100, WIDTH),
+                    Robot::wrap(8 + i / 8 - velocity.1 * 100, HEIGHT),
+                ),
+                velocity,
+            });
+        }
+
+        let lobby = Lobby::new(WIDTH as usize, HEIGHT as usize, robots).unwrap();
+
+        for metric_kind in MetricKind::ALL {
+            assert_eq!(
+                100,
+                lobby.best_times(metric_kind.metric().as_ref(), 1)[0].0,
+                "{}",
+                metric_kind.name()
+            );
+        }
+
+        assert_eq!(vec![100], lobby.least_random_times(1));
+        assert_eq!(-9.0, LongestRun.score(&lobby, 100));
+        assert_eq!(84.0, CompressedSize.score(&lobby, 100));
+
+        assert_eq!(
+            vec![MetricKind::LongestRun],
+            MetricKind::list_from_str("longest-run").unwrap()
+        );
+        assert_eq!(5, MetricKind::list_from_str("all").unwrap().len());
+        assert!(MetricKind::from_str("prettiness").is_err());
     }
 }
@@ -1,26 +1,80 @@
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
 use std::error::Error;
 use std::ops::Neg;
 use std::str::FromStr;
 use std::{env, fs};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut path = None;
+    let mut rules = MovementRules::default();
+    let mut top_k = None;
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--step-cost" => rules.step_cost = args.next().ok_or("Missing step cost")?.parse()?,
+            "--turn-cost" => rules.turn_cost = args.next().ok_or("Missing turn cost")?.parse()?,
+            "--reverse-cost" => {
+                rules.reverse_cost = Some(args.next().ok_or("Missing reverse cost")?.parse()?)
+            }
+            "--start-headings" => {
+                rules.start_headings =
+                    Direction::list_from_str(&args.next().ok_or("Missing start headings")?)?
+            }
+            "--end-headings" => {
+                rules.end_headings = Some(Direction::list_from_str(
+                    &args.next().ok_or("Missing end headings")?,
+                )?)
+            }
+            "--top-k" => top_k = Some(args.next().ok_or("Missing path count")?.parse()?),
+            _ => path = Some(arg),
+        }
+    }
 
-    if let Some(path) = args.get(1) {
-        let maze = ReindeerMaze::from_str(fs::read_to_string(path)?.as_str())?;
+    if let Some(path) = path {
+        let maze = ReindeerMaze::from_str(fs::read_to_string(path)?.as_str())?.with_rules(rules);
 
-        println!("Lowest possible score: {}", maze.lowest_score().unwrap());
+        const UNREACHABLE: &str = "Exit is not reachable with these movement rules";
+
+        println!(
+            "Lowest possible score: {}",
+            maze.lowest_score().map_err(|_| UNREACHABLE)?
+        );
 
         println!(
             "Tiles on optimal path: {}",
-            maze.tiles_on_optimal_path().unwrap()
+            maze.tiles_on_optimal_path().map_err(|_| UNREACHABLE)?
         );
 
+        if let Some(k) = top_k {
+            let best_paths = maze.best_paths(k);
+
+            for (i, path) in best_paths.iter().enumerate() {
+                println!(
+                    "Path {}: score {}, {} turns, {} steps",
+                    i + 1,
+                    path.score(),
+                    path.turns(),
+                    path.steps()
+                );
+            }
+
+            println!(
+                "Tiles shared by top {} paths: {}",
+                best_paths.len(),
+                ReindeerPath::shared_tiles(&best_paths).len()
+            );
+        }
+
         Ok(())
     } else {
-        Err("Usage: day16 INPUT_FILE_PATH".into())
+        Err(
+            "Usage: day16 [--step-cost N] [--turn-cost N] [--reverse-cost N] \
+            [--start-headings DIRECTIONS] [--end-headings DIRECTIONS] [--top-k N] INPUT_FILE_PATH"
+                .into(),
+        )
     }
 }
 
@@ -30,33 +84,71 @@ struct ReindeerMaze {
 
     start: usize,
     end: usize,
+
+    rules: MovementRules,
+}
+
+// Scores saturate rather than overflow, so enormous costs make routes unreachable rather than
+// wrapping around to look cheap
+struct MovementRules {
+    step_cost: u32,
+    turn_cost: u32,
+    // 180-degree turns aren't allowed at all if this is `None`
+    reverse_cost: Option<u32>,
+
+    start_headings: Vec<Direction>,
+    // Any heading is acceptable at the exit if this is `None`
+    end_headings: Option<Vec<Direction>>,
+}
+
+impl Default for MovementRules {
+    fn default() -> Self {
+        MovementRules {
+            step_cost: 1,
+            turn_cost: 1000,
+            reverse_cost: None,
+
+            start_headings: vec![Direction::Right],
+            end_headings: None,
+        }
+    }
 }
 
 impl ReindeerMaze {
-    const STEP_COST: u32 = 1;
-    const TURN_COST: u32 = 1000;
+    pub fn with_rules(self, rules: MovementRules) -> Self {
+        ReindeerMaze { rules, ..self }
+    }
 
     pub fn lowest_score(&self) -> Result<u32, ()> {
         self.lowest_score_at_exit(&self.lowest_scores())
     }
 
     fn lowest_score_at_exit(&self, lowest_scores: &[[Option<u32>; 4]]) -> Result<u32, ()> {
-        lowest_scores[self.end]
+        self.end_headings()
             .iter()
-            .filter_map(|&score| score)
+            .filter_map(|&heading| lowest_scores[self.end][heading as usize])
             .min()
             .ok_or(())
     }
 
+    fn end_headings(&self) -> &[Direction] {
+        self.rules
+            .end_headings
+            .as_deref()
+            .unwrap_or(&Direction::ALL)
+    }
+
     fn lowest_scores(&self) -> Vec<[Option<u32>; 4]> {
         let mut priority_queue = BinaryHeap::new();
         let mut lowest_scores = vec![[None; 4]; self.tiles.len()];
 
-        priority_queue.push(ReindeerState {
-            index: self.start,
-            heading: Direction::Right,
-            score: 0,
-        });
+        for &heading in &self.rules.start_headings {
+            priority_queue.push(ReindeerState {
+                index: self.start,
+                heading,
+                score: 0,
+            });
+        }
 
         while let Some(ReindeerState {
             index,
@@ -64,8 +156,8 @@ impl ReindeerMaze {
             score,
         }) = priority_queue.pop()
         {
-            if score > lowest_scores[index][heading as usize].unwrap_or(u32::MAX) {
-                // We've already found a lower-cost way to get to this state
+            if score >= lowest_scores[index][heading as usize].unwrap_or(u32::MAX) {
+                // We've already found a way to get to this state that's at least as good
                 continue;
             } else {
                 // This is the new best way to get to this state
@@ -78,32 +170,15 @@ impl ReindeerMaze {
                 priority_queue.push(ReindeerState {
                     index: forward_index,
                     heading,
-                    score: score + Self::STEP_COST,
+                    score: score.saturating_add(self.rules.step_cost),
                 });
             }
 
-            let turns = {
-                let candidate_turns = match heading {
-                    Direction::Up | Direction::Down => [Direction::Left, Direction::Right],
-                    Direction::Left | Direction::Right => [Direction::Up, Direction::Down],
-                };
-
-                let mut turns = Vec::with_capacity(2);
-
-                for candidate_turn in candidate_turns {
-                    if self.tiles[self.next_index(index, candidate_turn)] == Tile::Empty {
-                        turns.push(candidate_turn);
-                    }
-                }
-
-                turns
-            };
-
-            for turn in turns {
+            for (turn, cost) in self.turns(heading) {
                 priority_queue.push(ReindeerState {
                     index,
                     heading: turn,
-                    score: score + Self::TURN_COST,
+                    score: score.saturating_add(cost),
                 });
             }
         }
@@ -111,49 +186,375 @@ impl ReindeerMaze {
         lowest_scores
     }
 
+    /// Returns the headings the reindeer can turn to from the given heading along with the cost
+    /// of each turn. Turns are symmetric, so these are also the headings from which the reindeer
+    /// could have turned to arrive at the given heading.
+    fn turns(&self, heading: Direction) -> Vec<(Direction, u32)> {
+        let mut turns = match heading {
+            Direction::Up | Direction::Down => vec![
+                (Direction::Left, self.rules.turn_cost),
+                (Direction::Right, self.rules.turn_cost),
+            ],
+            Direction::Left | Direction::Right => vec![
+                (Direction::Up, self.rules.turn_cost),
+                (Direction::Down, self.rules.turn_cost),
+            ],
+        };
+
+        if let Some(reverse_cost) = self.rules.reverse_cost {
+            turns.push((-heading, reverse_cost));
+        }
+
+        turns
+    }
+
     pub fn tiles_on_optimal_path(&self) -> Result<u32, ()> {
         let lowest_scores = self.lowest_scores();
+        let lowest_score = self.lowest_score_at_exit(&lowest_scores)?;
+
         let mut tiles_on_path = vec![false; self.tiles.len()];
-        let mut queue = vec![(self.end, self.lowest_score_at_exit(&lowest_scores)?)];
+        let mut explored = vec![[false; 4]; self.tiles.len()];
 
-        while let Some((index, score)) = queue.pop() {
+        let mut queue: Vec<(usize, Direction)> = self
+            .end_headings()
+            .iter()
+            .filter(|&&heading| lowest_scores[self.end][heading as usize] == Some(lowest_score))
+            .map(|&heading| (self.end, heading))
+            .collect();
+
+        while let Some((index, heading)) = queue.pop() {
+            if explored[index][heading as usize] {
+                continue;
+            }
+
+            explored[index][heading as usize] = true;
             tiles_on_path[index] = true;
 
-            if score == 0 {
-                // We're back to the start; bail out
+            let score = lowest_scores[index][heading as usize].unwrap();
+
+            // Find a neighbor that's a single, straight step back
+            let previous_index = self.next_index(index, -heading);
+
+            if self.tiles[previous_index] == Tile::Empty
+                && score >= self.rules.step_cost
+                && lowest_scores[previous_index][heading as usize]
+                    == Some(score - self.rules.step_cost)
+            {
+                queue.push((previous_index, heading));
+            }
+
+            // Find turns that lead to this state
+            for (previous_heading, cost) in self.turns(heading) {
+                if score >= cost
+                    && lowest_scores[index][previous_heading as usize] == Some(score - cost)
+                {
+                    queue.push((index, previous_heading));
+                }
+            }
+        }
+
+        Ok(tiles_on_path.iter().filter(|&&on_path| on_path).count() as u32)
+    }
+
+    /// Finds up to `k` distinct routes from the start to the exit in order of increasing score
+    /// using Yen's algorithm. Yen's algorithm runs over "moves" (the cheapest rotation in place
+    /// followed by a single step) rather than individual turns so that routes don't differ only
+    /// in how the reindeer spun around on a single tile.
+    pub fn best_paths(&self, k: usize) -> Vec<ReindeerPath> {
+        let mut best_paths: Vec<ReindeerPath> = Vec::with_capacity(k);
+        let mut candidates: Vec<ReindeerPath> = Vec::new();
+        let mut seen_paths = HashSet::new();
+
+        let start = ((self.start, None), 0);
+
+        match self.shortest_spur_path(start, &HashSet::new(), &HashSet::new()) {
+            Some(nodes) if k > 0 => {
+                let path = self.expand_path(nodes);
+
+                seen_paths.insert(path.key());
+                best_paths.push(path);
+            }
+            _ => return best_paths,
+        }
+
+        while best_paths.len() < k {
+            let previous = &best_paths[best_paths.len() - 1];
+
+            for i in 0..previous.nodes.len() - 1 {
+                let root = &previous.nodes[..=i];
+
+                // Block every tile on the root (whatever the heading) so the spur can't loop back
+                // through it
+                let blocked_tiles: HashSet<usize> =
+                    root.iter().map(|&((index, _), _)| index).collect();
+
+                let blocked_moves: HashSet<(Node, Node)> = best_paths
+                    .iter()
+                    .filter(|path| {
+                        path.nodes.len() > i + 1
+                            && path.nodes[..=i]
+                                .iter()
+                                .zip(root)
+                                .all(|((a, _), (b, _))| a == b)
+                    })
+                    .map(|path| (path.nodes[i].0, path.nodes[i + 1].0))
+                    .collect();
+
+                if let Some(spur_nodes) =
+                    self.shortest_spur_path(root[i], &blocked_tiles, &blocked_moves)
+                {
+                    let mut nodes = root[..i].to_vec();
+                    nodes.extend(spur_nodes);
+
+                    let path = self.expand_path(nodes);
+
+                    debug_assert!(path.is_simple());
+
+                    if seen_paths.insert(path.key()) {
+                        candidates.push(path);
+                    }
+                }
+            }
+
+            // Prefer the earliest-discovered candidate among those with equal scores
+            if let Some(best_candidate) = candidates
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, path)| path.score())
+                .map(|(i, _)| i)
+            {
+                best_paths.push(candidates.remove(best_candidate));
+            } else {
+                break;
+            }
+        }
+
+        best_paths
+    }
+
+    fn shortest_spur_path(
+        &self,
+        source: (Node, u32),
+        blocked_tiles: &HashSet<usize>,
+        blocked_moves: &HashSet<(Node, Node)>,
+    ) -> Option<Vec<(Node, u32)>> {
+        let mut priority_queue = BinaryHeap::new();
+        let mut lowest_scores = vec![None; self.tiles.len() * 5];
+        let mut previous_nodes = vec![None; self.tiles.len() * 5];
+
+        let (source_node, source_score) = source;
+        // Break ties between equal scores by the number of moves so far, so a detour that costs
+        // nothing (and would revisit a tile) never wins over the direct route
+        priority_queue.push(Reverse((source_score, 0, false, source_node, None)));
+
+        while let Some(Reverse((score, moves, finished, node, previous_node))) =
+            priority_queue.pop()
+        {
+            if score == u32::MAX {
+                // Saturated scores mean the costs are too large to reach this state at all
                 continue;
             }
 
-            // Find neighbors that are a single, straight step back
-            for direction in [
-                Direction::Up,
-                Direction::Down,
-                Direction::Left,
-                Direction::Right,
-            ] {
-                let next_index = self.next_index(index, direction);
+            if finished {
+                let mut nodes = vec![(node, lowest_scores[Self::node_id(node)].unwrap())];
 
-                if self.tiles[next_index] == Tile::Empty
-                    && lowest_scores[next_index][-direction as usize] == Some(score - 1)
+                while let Some(previous_node) =
+                    previous_nodes[Self::node_id(nodes[nodes.len() - 1].0)]
                 {
-                    queue.push((next_index, score - 1));
+                    nodes.push((
+                        previous_node,
+                        lowest_scores[Self::node_id(previous_node)].unwrap(),
+                    ));
                 }
+
+                nodes.reverse();
+
+                return Some(nodes);
             }
 
-            // Find turns that lead to this state
-            for direction in [
-                Direction::Up,
-                Direction::Down,
-                Direction::Left,
-                Direction::Right,
-            ] {
-                if lowest_scores[index][direction as usize] == Some(score - 1000) {
-                    queue.push((index, score - 1000));
+            if score >= lowest_scores[Self::node_id(node)].unwrap_or(u32::MAX) {
+                continue;
+            }
+
+            lowest_scores[Self::node_id(node)] = Some(score);
+            previous_nodes[Self::node_id(node)] = previous_node;
+
+            let (index, heading) = node;
+            let headings = self.node_headings(heading);
+
+            if index == self.end {
+                if let Some((cost, _)) = self.cheapest_rotation(headings, self.end_headings()) {
+                    priority_queue.push(Reverse((
+                        score.saturating_add(cost),
+                        moves,
+                        true,
+                        node,
+                        None,
+                    )));
+                }
+
+                continue;
+            }
+
+            for direction in Direction::ALL {
+                let next_index = self.next_index(index, direction);
+                let next_node = (next_index, Some(direction));
+
+                if self.tiles[next_index] == Tile::Empty
+                    && !blocked_tiles.contains(&next_index)
+                    && !blocked_moves.contains(&(node, next_node))
+                {
+                    if let Some((cost, _)) = self.cheapest_rotation(headings, &[direction]) {
+                        priority_queue.push(Reverse((
+                            score
+                                .saturating_add(cost)
+                                .saturating_add(self.rules.step_cost),
+                            moves + 1,
+                            false,
+                            next_node,
+                            Some(node),
+                        )));
+                    }
                 }
             }
         }
 
-        Ok(tiles_on_path.iter().filter(|&&on_path| on_path).count() as u32)
+        None
+    }
+
+    /// Expands a sequence of moves into the individual steps and turns that make up a path.
+    fn expand_path(&self, nodes: Vec<(Node, u32)>) -> ReindeerPath {
+        let mut states = Vec::new();
+        let mut score = 0;
+
+        let (start_index, _) = nodes[0].0;
+        let mut headings = self.node_headings(None).to_vec();
+
+        let destinations = nodes[1..]
+            .iter()
+            .map(|&((index, heading), _)| (index, heading.unwrap()));
+
+        for (index, heading) in destinations {
+            let previous_index = states
+                .last()
+                .map_or(start_index, |state: &ReindeerState| state.index);
+
+            score = self.push_rotation(&mut states, previous_index, &headings, &[heading], score);
+            score = score.saturating_add(self.rules.step_cost);
+
+            states.push(ReindeerState {
+                index,
+                heading,
+                score,
+            });
+
+            headings = vec![heading];
+        }
+
+        let final_index = states.last().map_or(start_index, |state| state.index);
+        self.push_rotation(
+            &mut states,
+            final_index,
+            &headings,
+            self.end_headings(),
+            score,
+        );
+
+        ReindeerPath { nodes, states }
+    }
+
+    /// Appends the states for the cheapest in-place rotation from any of the `from` headings to
+    /// any of the `to` headings (including the starting state if `states` is empty) and returns
+    /// the score after rotating.
+    fn push_rotation(
+        &self,
+        states: &mut Vec<ReindeerState>,
+        index: usize,
+        from: &[Direction],
+        to: &[Direction],
+        score: u32,
+    ) -> u32 {
+        let (_, rotation) = self.cheapest_rotation(from, to).unwrap();
+        let mut score = score;
+
+        if states.is_empty() {
+            states.push(ReindeerState {
+                index,
+                heading: rotation[0],
+                score,
+            });
+        }
+
+        for pair in rotation.windows(2) {
+            score = score.saturating_add(self.turn_cost(pair[0], pair[1]));
+
+            states.push(ReindeerState {
+                index,
+                heading: pair[1],
+                score,
+            });
+        }
+
+        score
+    }
+
+    /// Returns the headings the reindeer might have at the given node; the start node is
+    /// "facing" all of the configured start headings at once.
+    fn node_headings(&self, heading: Option<Direction>) -> &[Direction] {
+        match heading {
+            Some(heading) => &Direction::ALL[heading as usize..=heading as usize],
+            None => &self.rules.start_headings,
+        }
+    }
+
+    /// Finds the cheapest way to rotate in place from any of the `from` headings to any of the
+    /// `to` headings, returning the cost and the sequence of headings (including the first and
+    /// last) along the way.
+    fn cheapest_rotation(
+        &self,
+        from: &[Direction],
+        to: &[Direction],
+    ) -> Option<(u32, Vec<Direction>)> {
+        from.iter()
+            .flat_map(|&from| to.iter().map(move |&to| (from, to)))
+            .map(|(from, to)| {
+                let rotation = if from == to {
+                    vec![from]
+                } else if from == -to {
+                    match self.rules.reverse_cost {
+                        Some(reverse_cost)
+                            if reverse_cost <= self.rules.turn_cost.saturating_mul(2) =>
+                        {
+                            vec![from, to]
+                        }
+                        _ => vec![from, self.turns(from)[0].0, to],
+                    }
+                } else {
+                    vec![from, to]
+                };
+
+                let cost = rotation
+                    .windows(2)
+                    .map(|pair| self.turn_cost(pair[0], pair[1]))
+                    .fold(0, u32::saturating_add);
+
+                (cost, rotation)
+            })
+            .min_by_key(|(cost, _)| *cost)
+    }
+
+    fn turn_cost(&self, from: Direction, to: Direction) -> u32 {
+        if from == -to {
+            self.rules
+                .reverse_cost
+                .expect("Reversing must be allowed to reverse in a single turn")
+        } else {
+            self.rules.turn_cost
+        }
+    }
+
+    fn node_id((index, heading): Node) -> usize {
+        (index * 5) + heading.map_or(4, |heading| heading as usize)
     }
 
     fn next_index(&self, index: usize, direction: Direction) -> usize {
@@ -204,10 +605,76 @@ impl FromStr for ReindeerMaze {
 
             start: start.ok_or("Start tile not found")?,
             end: end.ok_or("End tile not found")?,
+
+            rules: MovementRules::default(),
         })
     }
 }
 
+// A tile index and the heading with which the reindeer stepped onto that tile, or no heading at
+// all for the start of a path
+type Node = (usize, Option<Direction>);
+
+struct ReindeerPath {
+    nodes: Vec<(Node, u32)>,
+    states: Vec<ReindeerState>,
+}
+
+impl ReindeerPath {
+    pub fn score(&self) -> u32 {
+        self.states[self.states.len() - 1].score
+    }
+
+    pub fn turns(&self) -> u32 {
+        self.states
+            .windows(2)
+            .filter(|pair| pair[0].index == pair[1].index)
+            .count() as u32
+    }
+
+    pub fn steps(&self) -> u32 {
+        self.states
+            .windows(2)
+            .filter(|pair| pair[0].index != pair[1].index)
+            .count() as u32
+    }
+
+    pub fn tiles(&self) -> Vec<usize> {
+        let mut tiles: Vec<usize> = self.states.iter().map(|state| state.index).collect();
+        tiles.dedup();
+
+        tiles
+    }
+
+    pub fn shared_tiles(paths: &[ReindeerPath]) -> Vec<usize> {
+        if let Some((first, rest)) = paths.split_first() {
+            let path_tiles: Vec<HashSet<usize>> = rest
+                .iter()
+                .map(|path| path.tiles().into_iter().collect())
+                .collect();
+
+            first
+                .tiles()
+                .into_iter()
+                .filter(|tile| path_tiles.iter().all(|tiles| tiles.contains(tile)))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Returns true if the path never returns to a tile it has already left.
+    pub fn is_simple(&self) -> bool {
+        let tiles = self.tiles();
+
+        tiles.iter().collect::<HashSet<_>>().len() == tiles.len()
+    }
+
+    fn key(&self) -> Vec<Node> {
+        self.nodes.iter().map(|&(node, _)| node).collect()
+    }
+}
+
 #[derive(Eq, PartialEq)]
 struct ReindeerState {
     index: usize,
@@ -237,7 +704,7 @@ enum Tile {
     Wall,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 enum Direction {
     Up,
     Down,
@@ -245,6 +712,35 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    fn list_from_str(s: &str) -> Result<Vec<Self>, Box<dyn Error>> {
+        s.split(',')
+            .map(|d| Direction::from_str(d.trim()))
+            .collect()
+    }
+}
+
+impl FromStr for Direction {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "up" | "north" | "n" => Ok(Direction::Up),
+            "down" | "south" | "s" => Ok(Direction::Down),
+            "left" | "west" | "w" => Ok(Direction::Left),
+            "right" | "east" | "e" => Ok(Direction::Right),
+            _ => Err(format!("Unrecognized direction: {}", s).into()),
+        }
+    }
+}
+
 impl Neg for Direction {
     type Output = Self;
 
@@ -337,4 +833,119 @@ mod test {
             assert_eq!(64, maze.tiles_on_optimal_path().unwrap());
         }
     }
+
+    #[test]
+    fn test_lowest_score_with_rules() {
+        {
+            let maze = ReindeerMaze::from_str(TEST_MAZE_SMALL)
+                .unwrap()
+                .with_rules(MovementRules {
+                    start_headings: Direction::ALL.to_vec(),
+                    ..Default::default()
+                });
+
+            assert_eq!(6036, maze.lowest_score().unwrap());
+        }
+
+        {
+            let maze = ReindeerMaze::from_str(TEST_MAZE_SMALL)
+                .unwrap()
+                .with_rules(MovementRules {
+                    end_headings: Some(vec![Direction::Down]),
+                    ..Default::default()
+                });
+
+            assert_eq!(9036, maze.lowest_score().unwrap());
+            assert_eq!(45, maze.tiles_on_optimal_path().unwrap());
+        }
+
+        {
+            let maze = ReindeerMaze::from_str(TEST_MAZE_SMALL)
+                .unwrap()
+                .with_rules(MovementRules {
+                    reverse_cost: Some(1500),
+                    end_headings: Some(vec![Direction::Down]),
+                    ..Default::default()
+                });
+
+            assert_eq!(8536, maze.lowest_score().unwrap());
+            assert_eq!(8536, maze.best_paths(1)[0].score());
+        }
+    }
+
+    #[test]
+    fn test_best_paths() {
+        let maze = ReindeerMaze::from_str(TEST_MAZE_SMALL).unwrap();
+        let best_paths = maze.best_paths(4);
+
+        assert_eq!(
+            vec![7036, 7036, 7036, 9040],
+            best_paths
+                .iter()
+                .map(|path| path.score())
+                .collect::<Vec<_>>()
+        );
+
+        assert!(best_paths[..3]
+            .iter()
+            .all(|path| path.turns() == 7 && path.steps() == 36));
+
+        let tiles_on_best_paths: HashSet<usize> = best_paths[..3]
+            .iter()
+            .flat_map(|path| path.tiles())
+            .collect();
+
+        assert_eq!(45, tiles_on_best_paths.len());
+        assert_eq!(30, ReindeerPath::shared_tiles(&best_paths[..3]).len());
+    }
+
+    #[test]
+    fn test_best_paths_are_simple() {
+        // The large maze only has 24 routes that never revisit a tile
+        for (maze, routes) in [(TEST_MAZE_SMALL, 30), (TEST_MAZE_LARGE, 24)] {
+            let maze = ReindeerMaze::from_str(maze).unwrap();
+            let best_paths = maze.best_paths(30);
+
+            assert_eq!(routes, best_paths.len());
+            assert!(best_paths.iter().all(ReindeerPath::is_simple));
+            assert!(best_paths
+                .windows(2)
+                .all(|pair| pair[0].score() <= pair[1].score()));
+        }
+    }
+
+    #[test]
+    fn test_best_paths_with_zero_costs() {
+        let maze = ReindeerMaze::from_str(TEST_MAZE_SMALL)
+            .unwrap()
+            .with_rules(MovementRules {
+                step_cost: 0,
+                turn_cost: 0,
+                reverse_cost: Some(0),
+                ..Default::default()
+            });
+
+        // Every one of the 48 simple routes is free
+        let best_paths = maze.best_paths(50);
+
+        assert_eq!(48, best_paths.len());
+        assert!(best_paths
+            .iter()
+            .all(|path| path.score() == 0 && path.is_simple()));
+    }
+
+    #[test]
+    fn test_lowest_score_with_huge_costs() {
+        let maze = ReindeerMaze::from_str(TEST_MAZE_SMALL)
+            .unwrap()
+            .with_rules(MovementRules {
+                turn_cost: u32::MAX,
+                reverse_cost: Some(u32::MAX),
+                ..Default::default()
+            });
+
+        // Every route needs at least one turn, so none can be scored
+        assert!(maze.lowest_score().is_err());
+        assert!(maze.best_paths(3).is_empty());
+    }
 }
@@ -1,124 +1,505 @@
 use std::collections::VecDeque;
-use std::env;
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{env, fs};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
-
-    if let Some(path) = args.get(1) {
-        let memory_region = MemoryRegion::new(
-            71,
-            BufReader::new(File::open(path)?)
-                .lines()
-                .map_while(Result::ok),
+    let mut path = None;
+    let mut size = None;
+    let mut start = None;
+    let mut exit = None;
+    let mut time = None;
+    let mut binary_search = false;
+    let mut timeline = false;
+    let mut render_time = None;
+    let mut render_path = None;
+    let mut frames_directory = None;
+    let mut frame_format = FrameFormat::Text;
+    let mut scale = 8;
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => size = Some(args.next().ok_or("Missing region size")?.parse()?),
+            "--start" => {
+                start = Some(MemoryRegion::parse_coordinate(
+                    &args.next().ok_or("Missing start coordinate")?,
+                )?)
+            }
+            "--exit" => {
+                exit = Some(MemoryRegion::parse_coordinate(
+                    &args.next().ok_or("Missing exit coordinate")?,
+                )?)
+            }
+            "--time" => time = Some(args.next().ok_or("Missing time")?.parse()?),
+            "--binary-search" => binary_search = true,
+            "--timeline" => timeline = true,
+            "--render" => render_time = Some(args.next().ok_or("Missing render time")?.parse()?),
+            "--render-output" => {
+                render_path = Some(PathBuf::from(args.next().ok_or("Missing render path")?))
+            }
+            "--export-frames" => {
+                frames_directory =
+                    Some(PathBuf::from(args.next().ok_or("Missing frame directory")?))
+            }
+            "--frame-format" => {
+                frame_format = args.next().ok_or("Missing frame format")?.parse()?
+            }
+            "--scale" => scale = args.next().ok_or("Missing scale")?.parse()?,
+            _ => path = Some(arg),
+        }
+    }
+
+    // PPM renders are binary, so they can't share standard output with the text report
+    if render_time.is_some() && frame_format == FrameFormat::Ppm && render_path.is_none() {
+        return Err("PPM renders must be written to a file with --render-output PATH".into());
+    }
+
+    if let Some(path) = path {
+        let falling_bytes = MemoryRegion::parse_coordinates(fs::read_to_string(path)?.as_str())?;
+
+        // Unless told otherwise, assume the region is just big enough to hold every falling byte
+        let size = size.unwrap_or_else(|| {
+            falling_bytes
+                .iter()
+                .map(|&(x, y)| x.max(y) + 1)
+                .max()
+                .unwrap_or(0)
+        });
+
+        // The puzzle looks at the first kilobyte for the full input and 12 of the 25 bytes for the
+        // example; "half of the bytes, up to a kilobyte" matches both
+        let time = time.unwrap_or((falling_bytes.len() / 2).min(1024));
+
+        let memory_region = MemoryRegion::from_coordinates(size, falling_bytes)?.with_endpoints(
+            start.unwrap_or((0, 0)),
+            exit.unwrap_or((size - 1, size - 1)),
         )?;
 
         println!(
-            "Shortest path at time 1024: {}",
-            memory_region.shortest_path(1024)?
+            "Shortest path at time {}: {}",
+            time,
+            memory_region.shortest_path(time)?
         );
 
-        let blocking_coordinate = memory_region.blocking_coordinate();
+        let blocking_coordinate = if binary_search {
+            memory_region.blocking_coordinate()
+        } else {
+            memory_region.blocking_coordinate_by_union_find()
+        };
 
-        println!(
-            "Coordinate that blocks path to exit: {},{}",
-            blocking_coordinate.0, blocking_coordinate.1
-        );
+        if let Some((x, y)) = blocking_coordinate {
+            println!("Coordinate that blocks path to exit: {},{}", x, y);
+        } else {
+            println!("No coordinate blocks path to exit");
+        }
+
+        if timeline {
+            for (time, shortest_path) in memory_region.shortest_path_timeline().iter().enumerate() {
+                if let Some(shortest_path) = shortest_path {
+                    println!("{}: {}", time, shortest_path);
+                } else {
+                    println!("{}: no path", time);
+                }
+            }
+        }
+
+        if let Some(render_time) = render_time {
+            if let Some(render_path) = render_path {
+                let mut writer = BufWriter::new(File::create(&render_path)?);
+
+                match frame_format {
+                    FrameFormat::Text => write!(writer, "{}", memory_region.render(render_time))?,
+                    FrameFormat::Ppm => memory_region.write_ppm(render_time, scale, &mut writer)?,
+                }
+
+                println!("Wrote render to {}", render_path.display());
+            } else {
+                print!("{}", memory_region.render(render_time));
+            }
+        }
+
+        if let Some(frames_directory) = frames_directory {
+            let frames = memory_region.export_animation(&frames_directory, frame_format, scale)?;
+
+            println!("Wrote {} frames to {}", frames, frames_directory.display());
+        }
 
         Ok(())
     } else {
-        Err("Usage: day18 INPUT_FILE_PATH".into())
+        Err(
+            "Usage: day18 [--size N] [--start X,Y] [--exit X,Y] [--time N] [--binary-search] \
+            [--timeline] [--render TIME [--render-output PATH]] [--export-frames DIRECTORY] [--frame-format text|ppm] \
+            [--scale N] INPUT_FILE_PATH"
+                .into(),
+        )
     }
 }
 
 struct MemoryRegion {
     size: usize,
     falling_bytes: Vec<usize>,
+
+    start: usize,
+    exit: usize,
 }
 
 impl MemoryRegion {
-    pub fn new(
+    pub fn from_coordinates(
         size: usize,
-        falling_bytes: impl IntoIterator<Item = String>,
+        falling_bytes: impl IntoIterator<Item = (usize, usize)>,
     ) -> Result<Self, Box<dyn Error>> {
+        if size == 0 {
+            return Err("Memory region must not be empty".into());
+        }
+
         Ok(Self {
             size,
             falling_bytes: falling_bytes
                 .into_iter()
-                .map(|line| {
-                    if let Some((x, y)) = line.split_once(',') {
-                        let x: usize = x.parse()?;
-                        let y: usize = y.parse()?;
-
+                .map(|(x, y)| {
+                    if x < size && y < size {
                         Ok((y * size) + x)
                     } else {
-                        Err("Could not parse line".into())
+                        Err(format!(
+                            "Byte at {},{} falls outside the {}x{} memory region",
+                            x, y, size, size
+                        )
+                        .into())
                     }
                 })
                 .collect::<Result<Vec<usize>, Box<dyn Error>>>()?,
+
+            start: 0,
+            exit: (size * size) - 1,
         })
     }
 
+    pub fn with_endpoints(
+        self,
+        start: (usize, usize),
+        exit: (usize, usize),
+    ) -> Result<Self, Box<dyn Error>> {
+        for (x, y) in [start, exit] {
+            if x >= self.size || y >= self.size {
+                return Err(format!(
+                    "Coordinate {},{} is outside the {}x{} memory region",
+                    x, y, self.size, self.size
+                )
+                .into());
+            }
+        }
+
+        Ok(Self {
+            start: (start.1 * self.size) + start.0,
+            exit: (exit.1 * self.size) + exit.0,
+            ..self
+        })
+    }
+
+    pub fn parse_coordinates(s: &str) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+        s.lines().map(Self::parse_coordinate).collect()
+    }
+
+    pub fn parse_coordinate(s: &str) -> Result<(usize, usize), Box<dyn Error>> {
+        if let Some((x, y)) = s.split_once(',') {
+            Ok((x.trim().parse()?, y.trim().parse()?))
+        } else {
+            Err("Could not parse line".into())
+        }
+    }
+
     pub fn shortest_path(&self, time: usize) -> Result<u32, Box<dyn Error>> {
-        let mut queue = VecDeque::from([(0, 0)]);
+        self.shortest_path_tiles(time)
+            .map(|path| (path.len() - 1) as u32)
+            .ok_or_else(|| "No path to exit".into())
+    }
+
+    /// Returns the length of the shortest path to the exit (if any) after each number of bytes
+    /// from zero through the total number of falling bytes has fallen.
+    pub fn shortest_path_timeline(&self) -> Vec<Option<u32>> {
+        let mut timeline = Vec::with_capacity(self.falling_bytes.len() + 1);
+        let mut on_path = vec![false; self.size * self.size];
+        let mut path = None;
+
+        for time in 0..=self.falling_bytes.len() {
+            // The shortest path can only get longer (or disappear) if a byte lands on it
+            if time == 0 || on_path[self.falling_bytes[time - 1]] {
+                path = self.shortest_path_tiles(time);
+
+                on_path.fill(false);
+
+                if let Some(path) = &path {
+                    path.iter().for_each(|&i| on_path[i] = true);
+                }
+            }
+
+            timeline.push(path.as_ref().map(|path| (path.len() - 1) as u32));
+        }
+
+        timeline
+    }
+
+    fn shortest_path_tiles(&self, time: usize) -> Option<Vec<usize>> {
+        let safe_coordinates = self.safe_coordinates(time);
+
+        if !safe_coordinates[self.start] {
+            return None;
+        }
+
+        let mut queue = VecDeque::from([self.start]);
+        let mut previous: Vec<Option<usize>> = vec![None; self.size * self.size];
         let mut explored = vec![false; self.size * self.size];
-        let safe_coordinates = {
-            let mut safe_coordinates = vec![true; self.size * self.size];
+        explored[self.start] = true;
 
-            self.falling_bytes[0..time]
-                .iter()
-                .for_each(|&i| safe_coordinates[i] = false);
+        while let Some(index) = queue.pop_front() {
+            if index == self.exit {
+                let mut path = vec![index];
 
-            safe_coordinates
-        };
+                while let Some(previous_index) = previous[path[path.len() - 1]] {
+                    path.push(previous_index);
+                }
+
+                path.reverse();
+
+                return Some(path);
+            }
+
+            for neighbor in self.safe_neighbors(index, &safe_coordinates) {
+                if !explored[neighbor] {
+                    explored[neighbor] = true;
+                    previous[neighbor] = Some(index);
+
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn safe_coordinates(&self, time: usize) -> Vec<bool> {
+        let mut safe_coordinates = vec![true; self.size * self.size];
+
+        self.falling_bytes[0..time.min(self.falling_bytes.len())]
+            .iter()
+            .for_each(|&i| safe_coordinates[i] = false);
+
+        safe_coordinates
+    }
+
+    /// Finds the first byte that cuts off the exit by binary searching over time, returning `None`
+    /// if the exit is never cut off.
+    pub fn blocking_coordinate(&self) -> Option<(usize, usize)> {
+        self.first_time_without_exit()
+            .map(|time| self.coordinate(self.falling_bytes[time - 1]))
+    }
 
-        let exit_index = (self.size * self.size) - 1;
+    /// Finds the first byte that cuts off the exit by "un-dropping" bytes in reverse order and
+    /// tracking connected groups of safe coordinates, returning `None` if the exit is never cut
+    /// off.
+    pub fn blocking_coordinate_by_union_find(&self) -> Option<(usize, usize)> {
+        self.blocking_byte_by_union_find()
+            .map(|byte| self.coordinate(self.falling_bytes[byte]))
+    }
 
-        while let Some((index, elapsed_time)) = queue.pop_front() {
-            if index == exit_index {
-                return Ok(elapsed_time as u32);
+    /// Returns the position in the sequence of falling bytes of the first byte that cuts off the
+    /// exit.
+    fn blocking_byte_by_union_find(&self) -> Option<usize> {
+        // A coordinate might get hit more than once, but only the first hit matters
+        let mut first_impact = vec![None; self.size * self.size];
+
+        self.falling_bytes
+            .iter()
+            .enumerate()
+            .for_each(|(time, &i)| {
+                first_impact[i].get_or_insert(time);
+            });
+
+        let mut safe_coordinates: Vec<bool> = first_impact.iter().map(Option::is_none).collect();
+        let mut disjoint_set = DisjointSet::new(self.size * self.size);
+
+        for i in (0..safe_coordinates.len()).filter(|&i| safe_coordinates[i]) {
+            for neighbor in self.safe_neighbors(i, &safe_coordinates) {
+                disjoint_set.union(i, neighbor);
             }
+        }
 
-            if explored[index] {
+        let connected = |disjoint_set: &mut DisjointSet, safe_coordinates: &[bool]| {
+            safe_coordinates[self.start]
+                && safe_coordinates[self.exit]
+                && disjoint_set.find(self.start) == disjoint_set.find(self.exit)
+        };
+
+        if connected(&mut disjoint_set, &safe_coordinates) {
+            return None;
+        }
+
+        for (time, &i) in self.falling_bytes.iter().enumerate().rev() {
+            if first_impact[i] != Some(time) {
                 continue;
             }
 
-            explored[index] = true;
+            safe_coordinates[i] = true;
+
+            for neighbor in self.safe_neighbors(i, &safe_coordinates) {
+                disjoint_set.union(i, neighbor);
+            }
+
+            if connected(&mut disjoint_set, &safe_coordinates) {
+                return Some(time);
+            }
+        }
+
+        None
+    }
+
+    /// Draws the memory region after `time` bytes have fallen, including the shortest path to
+    /// the exit (if any) and, once it has fallen, the byte that cuts off the exit.
+    pub fn render(&self, time: usize) -> String {
+        self.cells(time, self.blocking_byte_by_union_find())
+            .chunks(self.size)
+            .map(|row| {
+                let mut line: String = row.iter().map(|&cell| char::from(cell)).collect();
+                line.push('\n');
+
+                line
+            })
+            .collect()
+    }
+
+    /// Writes a binary PPM image of the memory region after `time` bytes have fallen, with each
+    /// coordinate drawn as a `scale`-by-`scale` square.
+    pub fn write_ppm(
+        &self,
+        time: usize,
+        scale: usize,
+        writer: &mut impl Write,
+    ) -> Result<(), Box<dyn Error>> {
+        self.write_ppm_frame(
+            &self.cells(time, self.blocking_byte_by_union_find()),
+            scale,
+            writer,
+        )
+    }
+
+    /// Writes one frame per time step from zero until just after the exit gets cut off (or until
+    /// every byte has fallen if the exit never gets cut off) to the given directory, returning
+    /// the number of frames written.
+    pub fn export_animation(
+        &self,
+        directory: &Path,
+        format: FrameFormat,
+        scale: usize,
+    ) -> Result<usize, Box<dyn Error>> {
+        let blocking_byte = self.blocking_byte_by_union_find();
+        let last_time = blocking_byte.map_or(self.falling_bytes.len(), |byte| byte + 1);
+
+        fs::create_dir_all(directory)?;
+
+        for time in 0..=last_time {
+            let cells = self.cells(time, blocking_byte);
+
+            match format {
+                FrameFormat::Text => {
+                    let mut file = File::create(directory.join(format!("{:05}.txt", time)))?;
+
+                    for row in cells.chunks(self.size) {
+                        writeln!(
+                            file,
+                            "{}",
+                            row.iter().map(|&cell| char::from(cell)).collect::<String>()
+                        )?;
+                    }
+                }
+                FrameFormat::Ppm => {
+                    let mut writer =
+                        BufWriter::new(File::create(directory.join(format!("{:05}.ppm", time)))?);
+
+                    self.write_ppm_frame(&cells, scale, &mut writer)?;
+                }
+            }
+        }
+
+        Ok(last_time + 1)
+    }
+
+    fn write_ppm_frame(
+        &self,
+        cells: &[Cell],
+        scale: usize,
+        writer: &mut impl Write,
+    ) -> Result<(), Box<dyn Error>> {
+        let image_size = self.size * scale;
+
+        write!(writer, "P6\n{} {}\n255\n", image_size, image_size)?;
+
+        for row in cells.chunks(self.size) {
+            let pixels: Vec<u8> = row
+                .iter()
+                .flat_map(|&cell| cell.color().repeat(scale))
+                .collect();
 
-            queue.extend(
-                self.safe_neighbors(index, &safe_coordinates)
-                    .iter()
-                    .filter(|&&neighbor| !explored[neighbor])
-                    .map(|&neighbor| (neighbor, elapsed_time + 1)),
-            );
+            for _ in 0..scale {
+                writer.write_all(&pixels)?;
+            }
         }
 
-        Err("No path to exit".into())
+        Ok(())
     }
 
-    pub fn blocking_coordinate(&self) -> (usize, usize) {
-        let i = self.falling_bytes[self.last_time_to_exit()];
+    fn cells(&self, time: usize, blocking_byte: Option<usize>) -> Vec<Cell> {
+        let mut cells: Vec<Cell> = self
+            .safe_coordinates(time)
+            .iter()
+            .map(|&safe| if safe { Cell::Safe } else { Cell::Corrupted })
+            .collect();
+
+        if let Some(path) = self.shortest_path_tiles(time) {
+            path.iter().for_each(|&i| cells[i] = Cell::Path);
+        }
+
+        cells[self.start] = Cell::Start;
+        cells[self.exit] = Cell::Exit;
+
+        if let Some(byte) = blocking_byte.filter(|&byte| byte < time) {
+            cells[self.falling_bytes[byte]] = Cell::Blocking;
+        }
 
-        (i % self.size, i / self.size)
+        cells
     }
 
-    fn last_time_to_exit(&self) -> usize {
+    /// Returns the number of fallen bytes after which the exit first becomes unreachable, if it
+    /// ever does.
+    fn first_time_without_exit(&self) -> Option<usize> {
+        if self.shortest_path(self.falling_bytes.len()).is_ok() {
+            return None;
+        }
+
+        // The exit is always reachable after `left` bytes and never after `right`; with no bytes
+        // at all, nothing stands in the way
         let mut left = 0;
-        let mut right = self.falling_bytes.len() - 1;
+        let mut right = self.falling_bytes.len();
 
-        while left <= right {
+        while right - left > 1 {
             let mid = (left + right) / 2;
 
             if self.shortest_path(mid).is_ok() {
-                left = mid + 1;
+                left = mid;
             } else {
-                right = mid - 1;
+                right = mid;
             }
         }
 
-        right
+        Some(right)
+    }
+
+    fn coordinate(&self, index: usize) -> (usize, usize) {
+        (index % self.size, index / self.size)
     }
 
     fn safe_neighbors(&self, index: usize, safe_coordinates: &[bool]) -> Vec<usize> {
@@ -147,6 +528,110 @@ impl MemoryRegion {
     }
 }
 
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Cell {
+    Safe,
+    Corrupted,
+    Path,
+    Start,
+    Exit,
+    Blocking,
+}
+
+impl Cell {
+    fn color(&self) -> [u8; 3] {
+        match self {
+            Cell::Safe => [16, 16, 32],
+            Cell::Corrupted => [128, 128, 128],
+            Cell::Path => [255, 215, 0],
+            Cell::Start => [0, 200, 0],
+            Cell::Exit => [0, 128, 255],
+            Cell::Blocking => [255, 0, 0],
+        }
+    }
+}
+
+impl From<Cell> for char {
+    fn from(cell: Cell) -> Self {
+        match cell {
+            Cell::Safe => '.',
+            Cell::Corrupted => '#',
+            Cell::Path => 'O',
+            Cell::Start => 'S',
+            Cell::Exit => 'E',
+            Cell::Blocking => 'X',
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum FrameFormat {
+    Text,
+    Ppm,
+}
+
+impl FromStr for FrameFormat {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(FrameFormat::Text),
+            "ppm" => Ok(FrameFormat::Ppm),
+            _ => Err(format!("Unrecognized frame format: {}", s).into()),
+        }
+    }
+}
+
+struct DisjointSet {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl DisjointSet {
+    pub fn new(len: usize) -> Self {
+        DisjointSet {
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
+        }
+    }
+
+    pub fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        // Compress the path so future lookups are faster
+        let mut i = i;
+
+        while self.parents[i] != root {
+            let parent = self.parents[i];
+            self.parents[i] = root;
+            i = parent;
+        }
+
+        root
+    }
+
+    pub fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a != b {
+            // Attach the smaller tree to the larger one
+            let (smaller, larger) = if self.sizes[a] < self.sizes[b] {
+                (a, b)
+            } else {
+                (b, a)
+            };
+
+            self.parents[smaller] = larger;
+            self.sizes[larger] += self.sizes[smaller];
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -182,13 +667,137 @@ mod test {
 
     #[test]
     fn test_shortest_path() {
-        let memory_region = MemoryRegion::new(7, TEST_BYTES.lines().map(String::from)).unwrap();
+        let memory_region =
+            MemoryRegion::from_coordinates(7, MemoryRegion::parse_coordinates(TEST_BYTES).unwrap())
+                .unwrap();
         assert_eq!(22, memory_region.shortest_path(12).unwrap());
     }
 
     #[test]
     fn test_blocking_coordinate() {
-        let memory_region = MemoryRegion::new(7, TEST_BYTES.lines().map(String::from)).unwrap();
-        assert_eq!((6, 1), memory_region.blocking_coordinate());
+        let memory_region =
+            MemoryRegion::from_coordinates(7, MemoryRegion::parse_coordinates(TEST_BYTES).unwrap())
+                .unwrap();
+        assert_eq!(Some((6, 1)), memory_region.blocking_coordinate());
+
+        // Both approaches agree when the exit is never cut off
+        for open_region in [
+            MemoryRegion::from_coordinates(7, [(3, 3), (1, 1)]).unwrap(),
+            MemoryRegion::from_coordinates(7, []).unwrap(),
+        ] {
+            assert_eq!(None, open_region.blocking_coordinate());
+            assert_eq!(None, open_region.blocking_coordinate_by_union_find());
+        }
+
+        // ...and when the last byte cuts it off
+        let blocked_region = MemoryRegion::from_coordinates(2, [(0, 1), (1, 0)]).unwrap();
+        assert_eq!(Some((1, 0)), blocked_region.blocking_coordinate());
+        assert_eq!(
+            Some((1, 0)),
+            blocked_region.blocking_coordinate_by_union_find()
+        );
+    }
+
+    #[test]
+    fn test_from_coordinates_out_of_range() {
+        assert!(MemoryRegion::from_coordinates(7, [(6, 6)]).is_ok());
+        assert!(MemoryRegion::from_coordinates(7, [(7, 0)]).is_err());
+        assert!(MemoryRegion::from_coordinates(7, [(0, 7)]).is_err());
+    }
+
+    #[test]
+    fn test_shortest_path_with_endpoints() {
+        let memory_region =
+            MemoryRegion::from_coordinates(7, MemoryRegion::parse_coordinates(TEST_BYTES).unwrap())
+                .unwrap()
+                .with_endpoints((6, 6), (0, 0))
+                .unwrap();
+
+        assert_eq!(22, memory_region.shortest_path(12).unwrap());
+
+        assert!(MemoryRegion::from_coordinates(7, [])
+            .unwrap()
+            .with_endpoints((0, 0), (7, 7))
+            .is_err());
+    }
+
+    #[test]
+    fn test_blocking_coordinate_by_union_find() {
+        let memory_region =
+            MemoryRegion::from_coordinates(7, MemoryRegion::parse_coordinates(TEST_BYTES).unwrap())
+                .unwrap();
+
+        assert_eq!(
+            Some((6, 1)),
+            memory_region.blocking_coordinate_by_union_find()
+        );
+
+        let open_region = MemoryRegion::from_coordinates(7, [(3, 3)]).unwrap();
+        assert_eq!(None, open_region.blocking_coordinate_by_union_find());
+    }
+
+    #[test]
+    fn test_shortest_path_timeline() {
+        let memory_region =
+            MemoryRegion::from_coordinates(7, MemoryRegion::parse_coordinates(TEST_BYTES).unwrap())
+                .unwrap();
+
+        let timeline = memory_region.shortest_path_timeline();
+
+        assert_eq!(26, timeline.len());
+        assert_eq!(Some(12), timeline[0]);
+        assert_eq!(Some(22), timeline[12]);
+        assert!(timeline[20].is_some());
+        assert!(timeline[21..].iter().all(Option::is_none));
+
+        for (time, shortest_path) in timeline.iter().enumerate() {
+            assert_eq!(*shortest_path, memory_region.shortest_path(time).ok());
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let memory_region =
+            MemoryRegion::from_coordinates(7, MemoryRegion::parse_coordinates(TEST_BYTES).unwrap())
+                .unwrap();
+
+        let expected = indoc! {"
+            SO.#OOO
+            .O#OO#O
+            .OOO#OO
+            ...#OO#
+            ..#OO#.
+            .#.O#..
+            #.#OOOE
+        "};
+
+        assert_eq!(expected, memory_region.render(12));
+
+        let expected = indoc! {"
+            S..#...
+            .##..#X
+            .#..#..
+            ...#..#
+            ###..##
+            .##.###
+            #.#...E
+        "};
+
+        assert_eq!(expected, memory_region.render(21));
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let memory_region =
+            MemoryRegion::from_coordinates(7, MemoryRegion::parse_coordinates(TEST_BYTES).unwrap())
+                .unwrap();
+
+        let mut ppm = Vec::new();
+        memory_region.write_ppm(12, 2, &mut ppm).unwrap();
+
+        let header = b"P6\n14 14\n255\n";
+
+        assert!(ppm.starts_with(header));
+        assert_eq!(header.len() + (14 * 14 * 3), ppm.len());
     }
 }
@@ -1,12 +1,30 @@
 use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::iter::repeat_with;
 use std::str::FromStr;
+use std::time::Instant;
 use std::{env, fs};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut path = None;
+    let mut benchmark = false;
+    let mut explain = false;
+    let mut max_arrangements = 0;
 
-    if let Some(path) = args.get(1) {
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--benchmark" => benchmark = true,
+            "--explain" => explain = true,
+            "--arrangements" => {
+                max_arrangements = args.next().ok_or("Missing arrangement count")?.parse()?
+            }
+            _ => path = Some(arg),
+        }
+    }
+
+    if let Some(path) = path {
         let onsen = Onsen::from_str(fs::read_to_string(path)?.as_str())?;
         let possible_arrangements = onsen.possible_arrangements_by_towel();
 
@@ -23,23 +41,95 @@ fn main() -> Result<(), Box<dyn Error>> {
             possible_arrangements.iter().sum::<u64>()
         );
 
+        if explain || max_arrangements > 0 {
+            for (pattern, &arrangements) in onsen.patterns.iter().zip(&possible_arrangements) {
+                if arrangements > 0 {
+                    println!("{}: {} arrangements", pattern, arrangements);
+
+                    if explain {
+                        let fewest_towels = onsen.fewest_towels(pattern).unwrap();
+
+                        println!(
+                            "  Fewest towels ({}): {}",
+                            fewest_towels.len(),
+                            fewest_towels.join(" ")
+                        );
+                    }
+
+                    for arrangement in onsen.arrangements(pattern).take(max_arrangements) {
+                        println!("  {}", arrangement.join(" "));
+                    }
+                } else {
+                    let prefix_len = onsen.longest_possible_prefix(pattern);
+
+                    println!(
+                        "{}: impossible; longest possible prefix is \"{}\" ({} of {} stripes)",
+                        pattern,
+                        &pattern[..prefix_len],
+                        prefix_len,
+                        pattern.len()
+                    );
+                }
+            }
+        }
+
+        if benchmark {
+            let start = Instant::now();
+            let arrangements: u64 = onsen.possible_arrangements_by_towel().iter().sum();
+            println!("Trie: {} in {:?}", arrangements, start.elapsed());
+
+            let start = Instant::now();
+            let arrangements: u64 = onsen
+                .possible_arrangements_by_towel_by_length()
+                .iter()
+                .sum();
+            println!(
+                "Towels by length: {} in {:?}",
+                arrangements,
+                start.elapsed()
+            );
+        }
+
         Ok(())
     } else {
-        Err("Usage: day19 INPUT_FILE_PATH".into())
+        Err("Usage: day19 [--explain] [--arrangements N] [--benchmark] INPUT_FILE_PATH".into())
     }
 }
 
 struct Onsen {
+    towels: TowelTrie,
+    patterns: Vec<String>,
+
+    // The original towel-matching approach, kept around for comparison with the trie
     towels_by_length: Vec<Vec<String>>,
     min_towel_length: usize,
-
-    patterns: Vec<String>,
 }
 
 impl Onsen {
-    pub fn new(towels: Vec<String>, patterns: Vec<String>) -> Self {
-        let min_towel_length = towels.iter().map(|t| t.len()).min().unwrap();
-        let max_towel_length = towels.iter().map(|t| t.len()).max().unwrap();
+    const STRIPES: &'static str = "wubrg";
+
+    pub fn new(towels: Vec<String>, patterns: Vec<String>) -> Result<Self, OnsenParseError> {
+        // Empty towels can't contribute to any arrangement (and would confuse the length-based
+        // matcher), so there's no point in keeping them around
+        let towels: Vec<String> = towels
+            .into_iter()
+            .filter(|towel| !towel.is_empty())
+            .collect();
+
+        let min_towel_length = towels
+            .iter()
+            .map(|t| t.len())
+            .min()
+            .ok_or(OnsenParseError::NoTowels)?;
+
+        let max_towel_length = towels
+            .iter()
+            .map(|t| t.len())
+            .max()
+            .ok_or(OnsenParseError::NoTowels)?;
+
+        let mut towel_trie = TowelTrie::default();
+        towels.iter().for_each(|towel| towel_trie.insert(towel));
 
         let mut towels_by_length: Vec<Vec<String>> =
             repeat_with(Vec::new).take(max_towel_length + 1).collect();
@@ -48,12 +138,13 @@ impl Onsen {
             .into_iter()
             .for_each(|towel| towels_by_length[towel.len()].push(towel));
 
-        Onsen {
+        Ok(Onsen {
+            towels: towel_trie,
+            patterns,
+
             towels_by_length,
             min_towel_length,
-
-            patterns,
-        }
+        })
     }
 
     pub fn possible_arrangements_by_towel(&self) -> Vec<u64> {
@@ -64,6 +155,112 @@ impl Onsen {
     }
 
     fn possible_arrangements(&self, pattern: &str) -> u64 {
+        self.arrangements_by_prefix_length(pattern)[pattern.len()]
+    }
+
+    /// Returns the number of ways to arrange towels to match each prefix of the given pattern,
+    /// indexed by prefix length.
+    fn arrangements_by_prefix_length(&self, pattern: &str) -> Vec<u64> {
+        let pattern = pattern.as_bytes();
+
+        let mut paths = vec![0u64; pattern.len() + 1];
+        paths[0] = 1;
+
+        for start in 0..pattern.len() {
+            if paths[start] == 0 {
+                continue;
+            }
+
+            // Every towel that matches the pattern at this position extends every arrangement
+            // that reaches this position
+            for towel_len in self.towels.matches(&pattern[start..]) {
+                paths[start + towel_len] += paths[start];
+            }
+        }
+
+        paths
+    }
+
+    /// Lazily iterates over every distinct sequence of towels that matches the given pattern.
+    pub fn arrangements<'a>(&'a self, pattern: &'a str) -> Arrangements<'a> {
+        // Only bother exploring positions from which the rest of the pattern can be matched
+        let mut completable = vec![false; pattern.len() + 1];
+        completable[pattern.len()] = true;
+
+        for start in (0..pattern.len()).rev() {
+            completable[start] = self
+                .towels
+                .matches(&pattern.as_bytes()[start..])
+                .any(|towel_len| completable[start + towel_len]);
+        }
+
+        let stack = if completable[0] {
+            vec![(0, Vec::new())]
+        } else {
+            Vec::new()
+        };
+
+        Arrangements {
+            towels: &self.towels,
+            pattern,
+            completable,
+            stack,
+        }
+    }
+
+    /// Finds an arrangement of towels that matches the given pattern using as few towels as
+    /// possible.
+    pub fn fewest_towels<'a>(&self, pattern: &'a str) -> Option<Vec<&'a str>> {
+        // For each prefix length, the fewest towels needed to match that prefix and the length
+        // of the last towel used
+        let mut fewest: Vec<Option<(usize, usize)>> = vec![None; pattern.len() + 1];
+        fewest[0] = Some((0, 0));
+
+        for start in 0..pattern.len() {
+            if let Some((towels, _)) = fewest[start] {
+                for towel_len in self.towels.matches(&pattern.as_bytes()[start..]) {
+                    if fewest[start + towel_len].is_none_or(|(t, _)| towels + 1 < t) {
+                        fewest[start + towel_len] = Some((towels + 1, towel_len));
+                    }
+                }
+            }
+        }
+
+        fewest[pattern.len()]?;
+
+        let mut arrangement = Vec::new();
+        let mut end = pattern.len();
+
+        while end > 0 {
+            let (_, towel_len) = fewest[end].unwrap();
+
+            arrangement.push(&pattern[end - towel_len..end]);
+            end -= towel_len;
+        }
+
+        arrangement.reverse();
+
+        Some(arrangement)
+    }
+
+    /// Returns the length of the longest prefix of the given pattern that can be matched by some
+    /// arrangement of towels; this is the whole pattern for possible patterns, and explains where
+    /// things go wrong for impossible ones.
+    pub fn longest_possible_prefix(&self, pattern: &str) -> usize {
+        self.arrangements_by_prefix_length(pattern)
+            .iter()
+            .rposition(|&arrangements| arrangements > 0)
+            .unwrap_or(0)
+    }
+
+    pub fn possible_arrangements_by_towel_by_length(&self) -> Vec<u64> {
+        self.patterns
+            .iter()
+            .map(|pattern| self.possible_arrangements_by_length(pattern))
+            .collect()
+    }
+
+    fn possible_arrangements_by_length(&self, pattern: &str) -> u64 {
         let mut paths = vec![0u64; pattern.len() + 1];
         paths[0] = 1;
 
@@ -82,23 +279,260 @@ impl Onsen {
 
         paths[paths.len() - 1]
     }
+
+    fn validate_stripes(s: &str, line: usize, column: usize) -> Result<(), OnsenParseError> {
+        if let Some((i, stripe)) = s
+            .chars()
+            .enumerate()
+            .find(|(_, c)| !Self::STRIPES.contains(*c))
+        {
+            Err(OnsenParseError::InvalidStripe {
+                line,
+                column: column + i,
+                stripe,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+struct Arrangements<'a> {
+    towels: &'a TowelTrie,
+    pattern: &'a str,
+
+    completable: Vec<bool>,
+    stack: Vec<(usize, Vec<&'a str>)>,
+}
+
+impl<'a> Iterator for Arrangements<'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((start, arrangement)) = self.stack.pop() {
+            if start == self.pattern.len() {
+                return Some(arrangement);
+            }
+
+            let towel_lengths: Vec<usize> = self
+                .towels
+                .matches(&self.pattern.as_bytes()[start..])
+                .filter(|&towel_len| self.completable[start + towel_len])
+                .collect();
+
+            // Push longer towels first so shorter towels come off the stack first
+            for &towel_len in towel_lengths.iter().rev() {
+                let mut next_arrangement = arrangement.clone();
+                next_arrangement.push(&self.pattern[start..start + towel_len]);
+
+                self.stack.push((start + towel_len, next_arrangement));
+            }
+        }
+
+        None
+    }
+}
+
+#[derive(Default)]
+struct TowelTrie {
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: Vec<(u8, usize)>,
+    terminal: bool,
+}
+
+impl TowelTrie {
+    pub fn insert(&mut self, towel: &str) {
+        if self.nodes.is_empty() {
+            self.nodes.push(TrieNode::default());
+        }
+
+        let mut node = 0;
+
+        for &stripe in towel.as_bytes() {
+            node = if let Some(child) = self.child(node, stripe) {
+                child
+            } else {
+                self.nodes.push(TrieNode::default());
+
+                let child = self.nodes.len() - 1;
+                self.nodes[node].children.push((stripe, child));
+
+                child
+            };
+        }
+
+        self.nodes[node].terminal = true;
+    }
+
+    /// Returns an iterator over the lengths of all towels that are prefixes of the given pattern.
+    pub fn matches<'a>(&'a self, pattern: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        pattern
+            .iter()
+            .scan(Some(0), |node, &stripe| {
+                *node = node.and_then(|n| self.child(n, stripe));
+                *node
+            })
+            .enumerate()
+            .filter(|&(_, node)| self.nodes[node].terminal)
+            .map(|(i, _)| i + 1)
+    }
+
+    fn child(&self, node: usize, stripe: u8) -> Option<usize> {
+        self.nodes
+            .get(node)?
+            .children
+            .iter()
+            .find(|&&(s, _)| s == stripe)
+            .map(|&(_, child)| child)
+    }
 }
 
 impl FromStr for Onsen {
-    type Err = Box<dyn Error>;
+    type Err = OnsenParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some((towels, patterns)) = s.split_once("\n\n") {
-            let towels: Vec<String> = towels.split(", ").map(String::from).collect();
-            let patterns = patterns.lines().map(String::from).collect();
+        // `lines` takes care of both "\n" and "\r\n" line endings; line numbers start at 1
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .skip_while(|(_, line)| line.trim().is_empty())
+            .peekable();
 
-            Ok(Onsen::new(towels, patterns))
-        } else {
-            Err("Could not parse towels".into())
+        let mut towels = Vec::new();
+        let mut continues = true;
+
+        while continues {
+            let Some((line_number, line)) = lines.next_if(|(_, line)| !line.trim().is_empty())
+            else {
+                break;
+            };
+
+            let mut segments: Vec<(usize, &str)> = Vec::new();
+            let mut column = 1;
+
+            for segment in line.split(',') {
+                segments.push((column, segment));
+                column += segment.chars().count() + 1;
+            }
+
+            // A trailing comma means the list of towels continues on the next line; otherwise, the
+            // list ends here
+            continues = segments.len() > 1 && segments[segments.len() - 1].1.trim().is_empty();
+
+            if continues {
+                segments.pop();
+            }
+
+            for (column, segment) in segments {
+                let towel = segment.trim();
+                let column = column + segment.chars().take_while(|c| c.is_whitespace()).count();
+
+                if towel.is_empty() {
+                    return Err(OnsenParseError::EmptyTowel {
+                        line: line_number,
+                        column,
+                    });
+                }
+
+                Self::validate_stripes(towel, line_number, column)?;
+                towels.push(String::from(towel));
+            }
+        }
+
+        if towels.is_empty() {
+            return Err(OnsenParseError::NoTowels);
+        }
+
+        if lines.next_if(|(_, line)| line.trim().is_empty()).is_none() {
+            return Err(OnsenParseError::MissingPatterns);
+        }
+
+        let mut patterns: Vec<(usize, &str)> = lines.collect();
+
+        // Trailing blank lines are fine; blank lines between patterns are not
+        while patterns
+            .last()
+            .is_some_and(|(_, pattern)| pattern.trim().is_empty())
+        {
+            patterns.pop();
+        }
+
+        if patterns.is_empty() {
+            return Err(OnsenParseError::MissingPatterns);
+        }
+
+        let patterns = patterns
+            .into_iter()
+            .map(|(line_number, line)| {
+                let pattern = line.trim();
+
+                if pattern.is_empty() {
+                    Err(OnsenParseError::EmptyPattern { line: line_number })
+                } else {
+                    let column = 1 + line.chars().take_while(|c| c.is_whitespace()).count();
+                    Self::validate_stripes(pattern, line_number, column)?;
+
+                    Ok(String::from(pattern))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Onsen::new(towels, patterns)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum OnsenParseError {
+    NoTowels,
+    MissingPatterns,
+    EmptyTowel {
+        line: usize,
+        column: usize,
+    },
+    EmptyPattern {
+        line: usize,
+    },
+    InvalidStripe {
+        line: usize,
+        column: usize,
+        stripe: char,
+    },
+}
+
+impl Display for OnsenParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OnsenParseError::NoTowels => write!(f, "No towels found"),
+            OnsenParseError::MissingPatterns => {
+                write!(
+                    f,
+                    "Expected a blank line followed by patterns after the towels"
+                )
+            }
+            OnsenParseError::EmptyTowel { line, column } => {
+                write!(f, "Empty towel at line {}, column {}", line, column)
+            }
+            OnsenParseError::EmptyPattern { line } => write!(f, "Empty pattern at line {}", line),
+            OnsenParseError::InvalidStripe {
+                line,
+                column,
+                stripe,
+            } => write!(
+                f,
+                "Unexpected stripe '{}' at line {}, column {}",
+                stripe, line, column
+            ),
         }
     }
 }
 
+impl Error for OnsenParseError {}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -127,4 +561,146 @@ mod test {
             onsen.possible_arrangements_by_towel()
         );
     }
+
+    #[test]
+    fn test_towel_trie() {
+        let mut trie = TowelTrie::default();
+
+        ["r", "wr", "b", "rb", "rbg"]
+            .iter()
+            .for_each(|towel| trie.insert(towel));
+
+        assert_eq!(vec![1, 2, 3], trie.matches(b"rbgw").collect::<Vec<_>>());
+        assert_eq!(vec![2], trie.matches(b"wrb").collect::<Vec<_>>());
+        assert!(trie.matches(b"gwr").next().is_none());
+
+        let onsen = Onsen::from_str(TEST_ONSEN).unwrap();
+
+        assert_eq!(
+            onsen.possible_arrangements_by_towel_by_length(),
+            onsen.possible_arrangements_by_towel()
+        );
+    }
+
+    #[test]
+    fn test_arrangements() {
+        let onsen = Onsen::from_str(TEST_ONSEN).unwrap();
+
+        assert_eq!(
+            vec![
+                vec!["r", "r", "b", "g", "b", "r"],
+                vec!["r", "r", "b", "g", "br"]
+            ],
+            onsen.arrangements("rrbgbr").take(2).collect::<Vec<_>>()
+        );
+
+        for (pattern, &expected) in onsen
+            .patterns
+            .iter()
+            .zip(&onsen.possible_arrangements_by_towel())
+        {
+            let arrangements: Vec<Vec<&str>> = onsen.arrangements(pattern).collect();
+
+            assert_eq!(expected, arrangements.len() as u64);
+            assert!(arrangements
+                .iter()
+                .all(|arrangement| arrangement.concat() == *pattern));
+        }
+    }
+
+    #[test]
+    fn test_fewest_towels() {
+        let onsen = Onsen::from_str(TEST_ONSEN).unwrap();
+
+        // There are two equally-good arrangements for this pattern
+        let fewest_towels = onsen.fewest_towels("rrbgbr").unwrap();
+        assert_eq!(4, fewest_towels.len());
+        assert_eq!("rrbgbr", fewest_towels.concat());
+
+        assert_eq!(
+            Some(vec!["bwu", "r", "r", "g"]),
+            onsen.fewest_towels("bwurrg")
+        );
+        assert_eq!(None, onsen.fewest_towels("ubwu"));
+    }
+
+    #[test]
+    fn test_longest_possible_prefix() {
+        let onsen = Onsen::from_str(TEST_ONSEN).unwrap();
+
+        assert_eq!(6, onsen.longest_possible_prefix("rrbgbr"));
+        assert_eq!(0, onsen.longest_possible_prefix("ubwu"));
+        assert_eq!(4, onsen.longest_possible_prefix("bbrgwb"));
+    }
+
+    #[test]
+    fn test_parse_tolerant() {
+        let expected = Onsen::from_str(TEST_ONSEN).unwrap();
+
+        for s in [
+            TEST_ONSEN.replace('\n', "\r\n"),
+            format!("{}\n\n\n", TEST_ONSEN),
+            String::from("  r ,wr,  b,g,\n bwu ,rb, gb,br  \n\n  brwrr\nbggr\ngbbr\nrrbgbr\nubwu\nbwurrg\nbrgr\nbbrgwb"),
+        ] {
+            let onsen = Onsen::from_str(&s).unwrap();
+
+            assert_eq!(expected.patterns, onsen.patterns);
+            assert_eq!(
+                expected.possible_arrangements_by_towel(),
+                onsen.possible_arrangements_by_towel()
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Some(OnsenParseError::NoTowels), Onsen::from_str("").err());
+        assert_eq!(
+            Some(OnsenParseError::MissingPatterns),
+            Onsen::from_str("r, b\n").err()
+        );
+
+        assert_eq!(
+            Some(OnsenParseError::MissingPatterns),
+            Onsen::from_str("r, b\n\n").err()
+        );
+
+        assert_eq!(
+            Some(OnsenParseError::MissingPatterns),
+            Onsen::from_str("r, b\nrb\nbr\n").err()
+        );
+
+        assert_eq!(
+            Some(OnsenParseError::EmptyTowel { line: 1, column: 4 }),
+            Onsen::from_str("r, , b\n\nrb").err()
+        );
+
+        assert_eq!(
+            Some(OnsenParseError::EmptyPattern { line: 4 }),
+            Onsen::from_str("r, b\n\nrb\n\nbr\n").err()
+        );
+
+        assert_eq!(
+            Some(OnsenParseError::InvalidStripe {
+                line: 1,
+                column: 4,
+                stripe: 'x'
+            }),
+            Onsen::from_str("r, x\n\nrb").err()
+        );
+
+        assert_eq!(
+            Some(OnsenParseError::InvalidStripe {
+                line: 4,
+                column: 3,
+                stripe: 'q'
+            }),
+            Onsen::from_str("r, b\n\nrb\n rqb").err()
+        );
+
+        assert_eq!(
+            Some(OnsenParseError::NoTowels),
+            Onsen::new(vec![String::new()], vec![String::from("r")]).err()
+        );
+    }
 }
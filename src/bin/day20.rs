@@ -1,34 +1,108 @@
+use std::collections::{BTreeMap, VecDeque};
 use std::error::Error;
 use std::str::FromStr;
-use std::{env, fs};
+use std::time::Instant;
+use std::{env, fs, thread};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut path = None;
+    let mut radii = vec![2, 20];
+    let mut min_savings = 100;
+    let mut histogram = false;
+    let mut list = false;
+    let mut threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let mut benchmark = false;
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--radius" => radii = vec![args.next().ok_or("Missing cheat radius")?.parse()?],
+            "--min-savings" => {
+                min_savings = args.next().ok_or("Missing minimum savings")?.parse()?
+            }
+            "--histogram" => histogram = true,
+            "--list" => list = true,
+            "--threads" => threads = args.next().ok_or("Missing thread count")?.parse()?,
+            "--benchmark" => benchmark = true,
+            _ => path = Some(arg),
+        }
+    }
 
-    if let Some(path) = args.get(1) {
+    if let Some(path) = path {
         let racetrack = RaceTrack::from_str(fs::read_to_string(path)?.as_str())?;
 
-        println!(
-            "2-picosecond cheats saving at least 100 picoseconds: {}",
-            racetrack
-                .cheats(2)
-                .iter()
-                .filter(|&&savings| savings >= 100)
-                .count()
-        );
+        for radius in radii {
+            println!(
+                "{}-picosecond cheats saving at least {} picoseconds: {}",
+                radius,
+                min_savings,
+                racetrack.count_cheats(radius, min_savings, threads)?
+            );
+
+            if list || histogram {
+                let cheats: Vec<Cheat> = racetrack
+                    .cheats(radius)?
+                    .into_iter()
+                    .filter(|cheat| cheat.savings >= min_savings)
+                    .collect();
+
+                if list {
+                    for cheat in &cheats {
+                        println!(
+                            "  {},{} -> {},{}: {} picoseconds, saves {}",
+                            cheat.start.0,
+                            cheat.start.1,
+                            cheat.end.0,
+                            cheat.end.1,
+                            cheat.length,
+                            cheat.savings
+                        );
+                    }
+                }
 
-        println!(
-            "20-picosecond cheats saving at least 100 picoseconds: {}",
-            racetrack
-                .cheats(20)
-                .iter()
-                .filter(|&&savings| savings >= 100)
-                .count()
-        );
+                if histogram {
+                    for (savings, count) in Cheat::histogram(&cheats) {
+                        println!("  {} cheats that save {} picoseconds", count, savings);
+                    }
+                }
+            }
+
+            if benchmark {
+                let start = Instant::now();
+                let cheats = racetrack
+                    .cheats(radius)?
+                    .iter()
+                    .filter(|cheat| cheat.savings >= min_savings)
+                    .count();
+                println!("  cheats: {} in {:?}", cheats, start.elapsed());
+
+                let start = Instant::now();
+                let cheats = racetrack.count_cheats(radius, min_savings, 1)?;
+                println!(
+                    "  count_cheats (1 thread): {} in {:?}",
+                    cheats,
+                    start.elapsed()
+                );
+
+                let start = Instant::now();
+                let cheats = racetrack.count_cheats(radius, min_savings, threads)?;
+                println!(
+                    "  count_cheats ({} threads): {} in {:?}",
+                    threads,
+                    cheats,
+                    start.elapsed()
+                );
+            }
+        }
 
         Ok(())
     } else {
-        Err("Usage: day20 INPUT_FILE_PATH".into())
+        Err(
+            "Usage: day20 [--radius N] [--min-savings N] [--histogram] [--list] [--threads N] \
+            [--benchmark] INPUT_FILE_PATH"
+                .into(),
+        )
     }
 }
 
@@ -41,29 +115,128 @@ struct RaceTrack {
 }
 
 impl RaceTrack {
-    pub fn cheats(&self, radius: usize) -> Vec<u32> {
-        let times_along_path = self.times_along_path();
+    /// Finds every cheat of at most `radius` picoseconds that would make the race shorter. The
+    /// track may branch; a cheat from `a` to `b` finishes the race in the time it takes to get
+    /// from the start to `a`, plus the length of the cheat, plus the time it takes to get from
+    /// `b` to the end.
+    pub fn cheats(&self, radius: usize) -> Result<Vec<Cheat>, Box<dyn Error>> {
+        let times_from_start = self.times_from(self.start);
+        let times_to_end = self.times_from(self.end);
+
+        let best_time = times_from_start[self.end].ok_or("End is not reachable from start")?;
+
         let mut cheats = Vec::new();
 
-        times_along_path
+        times_from_start
             .iter()
             .enumerate()
             .filter_map(|(i, elapsed_time)| elapsed_time.map(|t| (i, t)))
             .for_each(|(i, elapsed_time)| {
                 self.neighbors(i, radius)
                     .iter()
-                    .filter_map(|&neighbor| times_along_path[neighbor].map(|t| (neighbor, t)))
-                    .for_each(|(neighbor, neighbor_time)| {
+                    .filter_map(|&neighbor| times_to_end[neighbor].map(|t| (neighbor, t)))
+                    .for_each(|(neighbor, remaining_time)| {
                         let distance = self.distance(i, neighbor);
+                        let time = elapsed_time + distance + remaining_time;
 
-                        if neighbor_time > elapsed_time + distance {
+                        if time < best_time {
                             // This is actually a shortcut
-                            cheats.push(neighbor_time - elapsed_time - distance);
+                            cheats.push(Cheat {
+                                start: self.position(i),
+                                end: self.position(neighbor),
+                                length: distance,
+                                savings: best_time - time,
+                            });
                         }
                     });
             });
 
-        cheats
+        Ok(cheats)
+    }
+
+    /// Counts cheats of at most `radius` picoseconds that save at least `min_savings` picoseconds
+    /// without building a list of cheats along the way. Rather than collecting the neighbors of
+    /// every track tile, this walks a precomputed table of offsets within the cheat radius, and
+    /// can split the track among several threads.
+    pub fn count_cheats(
+        &self,
+        radius: usize,
+        min_savings: u32,
+        threads: usize,
+    ) -> Result<usize, Box<dyn Error>> {
+        let times_from_start = self.times_from(self.start);
+        let times_to_end = self.times_from(self.end);
+
+        let best_time = times_from_start[self.end].ok_or("End is not reachable from start")?;
+
+        let width = self.width as isize;
+        let height = (self.tiles.len() / self.width) as isize;
+
+        // No two tiles are farther apart than opposite corners, so a larger radius only adds
+        // offsets that land off the track
+        let radius = radius.min(self.width + self.tiles.len() / self.width - 2) as isize;
+        let offsets: Vec<(isize, isize, u32)> = (-radius..=radius)
+            .flat_map(|dy: isize| {
+                let dx_max = radius - dy.abs();
+                (-dx_max..=dx_max).map(move |dx| (dx, dy, (dx.abs() + dy.abs()) as u32))
+            })
+            .filter(|&(_, _, distance)| distance > 0)
+            .collect();
+
+        let count_from = |tiles: &[Option<u32>], first_index: usize| -> usize {
+            let mut count = 0;
+
+            for (i, elapsed_time) in tiles.iter().enumerate() {
+                if let Some(elapsed_time) = elapsed_time {
+                    let index = first_index + i;
+                    let (x, y) = ((index % self.width) as isize, (index / self.width) as isize);
+
+                    for &(dx, dy, distance) in &offsets {
+                        let (x_neighbor, y_neighbor) = (x + dx, y + dy);
+
+                        if x_neighbor < 0
+                            || y_neighbor < 0
+                            || x_neighbor >= width
+                            || y_neighbor >= height
+                        {
+                            continue;
+                        }
+
+                        if let Some(remaining_time) =
+                            times_to_end[(y_neighbor * width + x_neighbor) as usize]
+                        {
+                            // Cheats that don't save any time at all aren't really cheats
+                            if elapsed_time + distance + remaining_time + min_savings.max(1)
+                                <= best_time
+                            {
+                                count += 1;
+                            }
+                        }
+                    }
+                }
+            }
+
+            count
+        };
+
+        if threads <= 1 {
+            Ok(count_from(&times_from_start, 0))
+        } else {
+            let chunk_size = times_from_start.len().div_ceil(threads);
+
+            Ok(thread::scope(|scope| {
+                let count_from = &count_from;
+
+                times_from_start
+                    .chunks(chunk_size)
+                    .enumerate()
+                    .map(|(i, chunk)| scope.spawn(move || count_from(chunk, i * chunk_size)))
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .sum()
+            }))
+        }
     }
 
     fn neighbors(&self, index: usize, radius: usize) -> Vec<usize> {
@@ -91,6 +264,10 @@ impl RaceTrack {
         neighbors
     }
 
+    fn position(&self, index: usize) -> (usize, usize) {
+        (index % self.width, index / self.width)
+    }
+
     fn distance(&self, a: usize, b: usize) -> u32 {
         let (x_a, y_a) = (a % self.width, a / self.width);
         let (x_b, y_b) = (b % self.width, b / self.width);
@@ -98,34 +275,24 @@ impl RaceTrack {
         (x_a.abs_diff(x_b) + y_a.abs_diff(y_b)) as u32
     }
 
-    fn times_along_path(&self) -> Vec<Option<u32>> {
-        let mut distances = vec![None; self.tiles.len()];
-        distances[self.start] = Some(0);
+    fn times_from(&self, origin: usize) -> Vec<Option<u32>> {
+        let mut times = vec![None; self.tiles.len()];
+        times[origin] = Some(0);
 
-        let mut index = self.start;
-        let mut distance = 0;
+        let mut queue = VecDeque::from([origin]);
 
-        // The problem statement asserts that there's exactly one path through the maze, and we
-        // additionally assert that the whole racetrack has a solid border of walls.
-        loop {
-            if index == self.end {
-                break;
-            }
+        while let Some(index) = queue.pop_front() {
+            let time = times[index].unwrap();
 
-            distance += 1;
-
-            let next = *[index + 1, index - 1, index + self.width, index - self.width]
-                .iter()
-                .find(|&&neighbor| {
-                    self.tiles[neighbor] == Tile::Track && distances[neighbor].is_none()
-                })
-                .unwrap();
-
-            distances[next] = Some(distance);
-            index = next;
+            for neighbor in self.neighbors(index, 1) {
+                if self.tiles[neighbor] == Tile::Track && times[neighbor].is_none() {
+                    times[neighbor] = Some(time + 1);
+                    queue.push_back(neighbor);
+                }
+            }
         }
 
-        distances
+        times
     }
 }
 
@@ -139,6 +306,10 @@ impl FromStr for RaceTrack {
             .ok_or("String must contain at least one line")?
             .len();
 
+        if width == 0 {
+            return Err("Race track must not be empty".into());
+        }
+
         let mut start = None;
         let mut end = None;
 
@@ -175,6 +346,28 @@ impl FromStr for RaceTrack {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Cheat {
+    start: (usize, usize),
+    end: (usize, usize),
+    length: u32,
+    savings: u32,
+}
+
+impl Cheat {
+    /// Groups cheats by the number of picoseconds they save, returning (savings, count) pairs in
+    /// order of increasing savings.
+    pub fn histogram(cheats: &[Cheat]) -> Vec<(u32, usize)> {
+        let mut histogram = BTreeMap::new();
+
+        cheats
+            .iter()
+            .for_each(|cheat| *histogram.entry(cheat.savings).or_insert(0) += 1);
+
+        histogram.into_iter().collect()
+    }
+}
+
 #[derive(Eq, PartialEq)]
 enum Tile {
     Track,
@@ -209,7 +402,7 @@ mod test {
         let racetrack = RaceTrack::from_str(TEST_RACETRACK).unwrap();
 
         {
-            let cheats = racetrack.cheats(2);
+            let cheats = racetrack.cheats(2).unwrap();
 
             for (n, savings) in [
                 (14, 2),
@@ -224,12 +417,12 @@ mod test {
                 (1, 40),
                 (1, 64),
             ] {
-                assert_eq!(n, cheats.iter().filter(|&&s| s == savings).count());
+                assert_eq!(n, cheats.iter().filter(|c| c.savings == savings).count());
             }
         }
 
         {
-            let cheats = racetrack.cheats(20);
+            let cheats = racetrack.cheats(20).unwrap();
 
             for (n, savings) in [
                 (32, 50),
@@ -243,7 +436,110 @@ mod test {
                 (12, 66),
                 (14, 68),
             ] {
-                assert_eq!(n, cheats.iter().filter(|&&s| s == savings).count());
+                assert_eq!(n, cheats.iter().filter(|c| c.savings == savings).count());
+            }
+        }
+    }
+
+    #[test]
+    fn test_cheat_details() {
+        let racetrack = RaceTrack::from_str(TEST_RACETRACK).unwrap();
+        let cheats = racetrack.cheats(2).unwrap();
+
+        // The example's 64-picosecond cheat passes through the wall right next to the end
+        assert_eq!(
+            vec![Cheat {
+                start: (7, 7),
+                end: (5, 7),
+                length: 2,
+                savings: 64,
+            }],
+            cheats
+                .iter()
+                .copied()
+                .filter(|cheat| cheat.savings == 64)
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            vec![
+                (2, 14),
+                (4, 14),
+                (6, 2),
+                (8, 4),
+                (10, 2),
+                (12, 3),
+                (20, 1),
+                (36, 1),
+                (38, 1),
+                (40, 1),
+                (64, 1),
+            ],
+            Cheat::histogram(&cheats)
+        );
+    }
+
+    #[test]
+    fn test_cheats_branching_track() {
+        // Two routes from start to end, and no wall around the edge of the track
+        let racetrack = RaceTrack::from_str(indoc! {"
+            S.....
+            ####..
+            E.....
+        "})
+        .unwrap();
+
+        assert_eq!(
+            vec![(2, 1), (4, 1), (6, 1), (8, 1)],
+            Cheat::histogram(&racetrack.cheats(2).unwrap())
+        );
+
+        let unreachable = RaceTrack::from_str(indoc! {"
+            S.#.E
+        "})
+        .unwrap();
+
+        assert!(unreachable.cheats(2).is_err());
+    }
+
+    #[test]
+    fn test_count_cheats_huge_radius() {
+        let racetrack = RaceTrack::from_str(TEST_RACETRACK).unwrap();
+
+        assert_eq!(
+            racetrack.count_cheats(28, 1, 1).unwrap(),
+            racetrack.count_cheats(100_000, 1, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_empty_racetrack() {
+        assert!(RaceTrack::from_str("").is_err());
+        assert!(RaceTrack::from_str("\nS.E").is_err());
+    }
+
+    #[test]
+    fn test_count_cheats() {
+        let racetrack = RaceTrack::from_str(TEST_RACETRACK).unwrap();
+
+        for radius in [2, 20] {
+            for min_savings in [0, 1, 50, 64, 76] {
+                let expected = racetrack
+                    .cheats(radius)
+                    .unwrap()
+                    .iter()
+                    .filter(|cheat| cheat.savings >= min_savings)
+                    .count();
+
+                assert_eq!(
+                    expected,
+                    racetrack.count_cheats(radius, min_savings, 1).unwrap()
+                );
+
+                assert_eq!(
+                    expected,
+                    racetrack.count_cheats(radius, min_savings, 4).unwrap()
+                );
             }
         }
     }
@@ -1,12 +1,39 @@
+use std::cmp::Reverse;
 use std::env;
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::thread;
+use std::time::Instant;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut path = None;
+    let mut threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let mut benchmark = false;
+    let mut sequence_length = 4;
+    let mut top = 0;
+    let mut show_buyers = false;
+    let mut jump = None;
+    let mut show_periods = false;
 
-    if let Some(path) = args.get(1) {
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threads" => threads = args.next().ok_or("Missing thread count")?.parse()?,
+            "--benchmark" => benchmark = true,
+            "--sequence-length" => {
+                sequence_length = args.next().ok_or("Missing sequence length")?.parse()?
+            }
+            "--top" => top = args.next().ok_or("Missing sequence count")?.parse()?,
+            "--buyers" => show_buyers = true,
+            "--jump" => jump = Some(args.next().ok_or("Missing iteration count")?.parse()?),
+            "--periods" => show_periods = true,
+            _ => path = Some(arg),
+        }
+    }
+
+    if let Some(path) = path {
         let monkey_market = MonkeyMarket {
             secret_numbers: BufReader::new(File::open(path)?)
                 .lines()
@@ -20,23 +47,341 @@ fn main() -> Result<(), Box<dyn Error>> {
             monkey_market.secret_number_sum(2000)
         );
 
+        if let Some(iterations) = jump {
+            println!(
+                "Secret number sum after {} iterations: {}",
+                iterations,
+                monkey_market.secret_number_sum_after(iterations)
+            );
+        }
+
+        if show_periods {
+            for (buyer, &seed) in monkey_market.secret_numbers.iter().enumerate() {
+                println!(
+                    "Buyer {} ({}): period {}",
+                    buyer + 1,
+                    seed,
+                    SecretNumbers::period(seed)
+                );
+            }
+        }
+
+        let best_sequence = monkey_market.max_bananas(2000, sequence_length, threads)?;
+
         println!(
-            "Max bananas after 2000 iterations: {}",
-            monkey_market.max_bananas(2000)
+            "Max bananas after 2000 iterations: {} (price changes {})",
+            best_sequence.bananas,
+            format_sequence(&best_sequence.sequence)
         );
 
+        if show_buyers {
+            for (buyer, bananas) in best_sequence.bananas_by_buyer.iter().enumerate() {
+                println!(
+                    "  Buyer {} ({}): {}",
+                    buyer + 1,
+                    monkey_market.secret_numbers[buyer],
+                    bananas
+                );
+            }
+        }
+
+        if top > 0 {
+            println!("Top {} sequences:", top);
+
+            for sequence_bananas in
+                monkey_market.top_sequences(2000, sequence_length, top, threads)?
+            {
+                println!(
+                    "  {}: {}",
+                    format_sequence(&sequence_bananas.sequence),
+                    sequence_bananas.bananas
+                );
+            }
+        }
+
+        if benchmark {
+            let start = Instant::now();
+            let sum = monkey_market.secret_number_sum(2000);
+            println!(
+                "Batched secret number sum: {} in {:?}",
+                sum,
+                start.elapsed()
+            );
+
+            let start = Instant::now();
+            let sum = monkey_market.secret_number_sum_by_buyer(2000);
+            println!(
+                "Per-buyer secret number sum: {} in {:?}",
+                sum,
+                start.elapsed()
+            );
+
+            let start = Instant::now();
+            let bananas = monkey_market.max_bananas(2000, 4, threads)?.bananas;
+            println!(
+                "Batched max bananas ({} threads): {} in {:?}",
+                threads,
+                bananas,
+                start.elapsed()
+            );
+
+            let start = Instant::now();
+            let bananas = monkey_market.max_bananas_by_buyer(2000);
+            println!(
+                "Per-buyer max bananas: {} in {:?}",
+                bananas,
+                start.elapsed()
+            );
+        }
+
         Ok(())
     } else {
-        Err("Usage: day22 INPUT_FILE_PATH".into())
+        Err(
+            "Usage: day22 [--sequence-length N] [--top N] [--buyers] [--jump N] [--periods] \
+            [--threads N] [--benchmark] INPUT_FILE_PATH"
+                .into(),
+        )
     }
 }
 
+fn format_sequence(sequence: &[i8]) -> String {
+    sequence
+        .iter()
+        .map(|delta| delta.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The longest supported sequence of price changes; longer sequences need prohibitively large
+/// tables of bananas by sequence
+const MAX_SEQUENCE_LENGTH: usize = 5;
+
+/// The most table entries all threads may allocate at once; each thread needs two tables with an
+/// entry for every distinct sequence, so long sequences run on fewer threads
+const MAX_TABLE_ENTRIES: usize = 1 << 25;
+
 struct MonkeyMarket {
     secret_numbers: Vec<u64>,
 }
 
 impl MonkeyMarket {
+    /// Sums each buyer's secret number after `iterations` steps by jumping ahead with a single
+    /// precomputed step matrix, so arbitrarily large iteration counts cost the same.
+    pub fn secret_number_sum_after(&self, iterations: u64) -> u64 {
+        if iterations == 0 {
+            return self.secret_numbers.iter().sum();
+        }
+
+        let jump = BitMatrix::step().pow(iterations);
+
+        self.secret_numbers
+            .iter()
+            .map(|&seed| jump.apply(seed & SECRET_NUMBER_MASK))
+            .sum()
+    }
+
     pub fn secret_number_sum(&self, iterations: usize) -> u64 {
+        self.secret_numbers
+            .chunks(LANES)
+            .map(|buyers| {
+                let mut lanes = SecretNumberLanes::new(buyers);
+
+                for _ in 0..iterations {
+                    lanes.advance();
+                }
+
+                lanes.n[..buyers.len()].iter().sum::<u64>()
+            })
+            .sum()
+    }
+
+    /// Finds the sequence of price changes that collects the most bananas, along with the
+    /// bananas contributed by each buyer.
+    pub fn max_bananas(
+        &self,
+        iterations: usize,
+        sequence_length: usize,
+        threads: usize,
+    ) -> Result<SequenceBananas, Box<dyn Error>> {
+        // If no sequence earns anything (say, because there are too few iterations to see a full
+        // sequence), any sequence is as good as another
+        Ok(self
+            .top_sequences(iterations, sequence_length, 1, threads)?
+            .pop()
+            .unwrap_or_else(|| SequenceBananas {
+                sequence: vec![0; sequence_length],
+                bananas: 0,
+                bananas_by_buyer: vec![0; self.secret_numbers.len()],
+            }))
+    }
+
+    /// Lists the `n` sequences of price changes that collect the most bananas, best first.
+    pub fn top_sequences(
+        &self,
+        iterations: usize,
+        sequence_length: usize,
+        n: usize,
+        threads: usize,
+    ) -> Result<Vec<SequenceBananas>, Box<dyn Error>> {
+        let bananas_by_sequence = self.bananas_by_sequence(iterations, sequence_length, threads)?;
+
+        let mut packed_sequences: Vec<usize> = (0..bananas_by_sequence.len())
+            .filter(|&packed_sequence| bananas_by_sequence[packed_sequence] > 0)
+            .collect();
+
+        packed_sequences
+            .sort_by_key(|&packed_sequence| Reverse(bananas_by_sequence[packed_sequence]));
+
+        Ok(packed_sequences
+            .into_iter()
+            .take(n)
+            .map(|packed_sequence| {
+                let sequence = Self::unpack_sequence(packed_sequence as u32, sequence_length);
+                let bananas_by_buyer = self.bananas_by_buyer(iterations, &sequence);
+
+                SequenceBananas {
+                    sequence,
+                    bananas: bananas_by_sequence[packed_sequence],
+                    bananas_by_buyer,
+                }
+            })
+            .collect())
+    }
+
+    /// Finds the bananas each buyer would pay at the first occurrence of the given sequence of
+    /// price changes.
+    pub fn bananas_by_buyer(&self, iterations: usize, sequence: &[i8]) -> Vec<u32> {
+        self.secret_numbers
+            .iter()
+            .map(|&seed| {
+                let prices: Vec<i8> = SecretNumbers::new(seed)
+                    .take(iterations + 1)
+                    .map(|n| (n % 10) as i8)
+                    .collect();
+
+                prices
+                    .windows(sequence.len() + 1)
+                    .find(|window| {
+                        window
+                            .windows(2)
+                            .map(|pair| pair[1] - pair[0])
+                            .eq(sequence.iter().copied())
+                    })
+                    .map_or(0, |window| window[sequence.len()] as u32)
+            })
+            .collect()
+    }
+
+    /// Totals the bananas collected by each packed sequence of price changes, advancing buyers in
+    /// batches of [`LANES`] and splitting buyers among `threads` threads.
+    fn bananas_by_sequence(
+        &self,
+        iterations: usize,
+        sequence_length: usize,
+        threads: usize,
+    ) -> Result<Vec<u32>, Box<dyn Error>> {
+        if !(1..=MAX_SEQUENCE_LENGTH).contains(&sequence_length) {
+            return Err(format!(
+                "Sequence length must be between 1 and {}",
+                MAX_SEQUENCE_LENGTH
+            )
+            .into());
+        }
+
+        let distinct_sequences = 19usize.pow(sequence_length as u32);
+        let threads = threads.min(MAX_TABLE_ENTRIES / (distinct_sequences * 2));
+
+        let bananas_by_sequence = |buyers: &[u64]| -> Vec<u32> {
+            let mut bananas_by_sequence = vec![0; distinct_sequences];
+
+            // Rather than clearing a "seen" table for every buyer, mark each sequence with the
+            // (1-based) index of the last buyer to encounter it
+            let mut last_buyer_by_sequence = vec![0u32; distinct_sequences];
+
+            // Generate prices for a whole batch of buyers in lockstep, then tally each buyer's
+            // price changes one buyer at a time
+            let mut prices = vec![[0u8; LANES]; iterations + 1];
+
+            for (batch, buyers) in buyers.chunks(LANES).enumerate() {
+                let mut lanes = SecretNumberLanes::new(buyers);
+                prices[0] = lanes.n.map(|n| (n % 10) as u8);
+
+                for prices in prices.iter_mut().skip(1) {
+                    lanes.advance();
+                    *prices = lanes.n.map(|n| (n % 10) as u8);
+                }
+
+                for lane in 0..buyers.len() {
+                    let buyer = (batch * LANES + lane + 1) as u32;
+                    let mut sequence = 0;
+
+                    for (i, window) in prices.windows(2).enumerate() {
+                        let (previous_price, price) = (window[0][lane], window[1][lane]);
+
+                        // Equivalent to `pack_sequence` over the most recent price changes
+                        sequence = (sequence * 19 + (price + 9 - previous_price) as usize)
+                            % distinct_sequences;
+
+                        if i + 1 >= sequence_length && last_buyer_by_sequence[sequence] != buyer {
+                            last_buyer_by_sequence[sequence] = buyer;
+                            bananas_by_sequence[sequence] += price as u32;
+                        }
+                    }
+                }
+            }
+
+            bananas_by_sequence
+        };
+
+        if threads <= 1 {
+            return Ok(bananas_by_sequence(&self.secret_numbers));
+        }
+
+        // Keep batches full for every thread but (possibly) the last
+        let chunk_size = self
+            .secret_numbers
+            .len()
+            .div_ceil(threads)
+            .next_multiple_of(LANES);
+
+        let mut tables: Vec<Vec<u32>> = thread::scope(|scope| {
+            let bananas_by_sequence = &bananas_by_sequence;
+
+            self.secret_numbers
+                .chunks(chunk_size)
+                .map(|buyers| scope.spawn(move || bananas_by_sequence(buyers)))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        let Some((totals, other_tables)) = tables.split_first_mut() else {
+            return Ok(vec![0; distinct_sequences]);
+        };
+
+        // Reduce the per-thread tables into the first one, with each thread taking its own range
+        // of sequences
+        let range_size = distinct_sequences.div_ceil(threads);
+        let other_tables = &*other_tables;
+
+        thread::scope(|scope| {
+            for (i, totals) in totals.chunks_mut(range_size).enumerate() {
+                scope.spawn(move || {
+                    for table in other_tables {
+                        for (total, bananas) in totals.iter_mut().zip(&table[i * range_size..]) {
+                            *total += bananas;
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(tables.swap_remove(0))
+    }
+
+    // The original one-buyer-at-a-time approaches, kept around for comparison
+    pub fn secret_number_sum_by_buyer(&self, iterations: usize) -> u64 {
         self.secret_numbers
             .iter()
             .map(|&seed| {
@@ -49,7 +394,7 @@ impl MonkeyMarket {
             .sum()
     }
 
-    pub fn max_bananas(&self, iterations: usize) -> u32 {
+    pub fn max_bananas_by_buyer(&self, iterations: usize) -> u32 {
         const SEQUENCE_LENGTH: usize = 4;
         const DISTINCT_SEQUENCES: usize = 19usize.pow(SEQUENCE_LENGTH as u32);
 
@@ -92,6 +437,55 @@ impl MonkeyMarket {
             .iter()
             .fold(0u32, |acc, &i| acc * 19 + ((i + 9) as u32))
     }
+
+    fn unpack_sequence(packed_sequence: u32, sequence_length: usize) -> Vec<i8> {
+        let mut sequence: Vec<i8> = (0..sequence_length)
+            .scan(packed_sequence, |packed_sequence, _| {
+                let delta = (*packed_sequence % 19) as i8 - 9;
+                *packed_sequence /= 19;
+
+                Some(delta)
+            })
+            .collect();
+
+        sequence.reverse();
+        sequence
+    }
+}
+
+struct SequenceBananas {
+    sequence: Vec<i8>,
+    bananas: u32,
+    bananas_by_buyer: Vec<u32>,
+}
+
+/// The number of buyers advanced in lockstep by [`SecretNumberLanes`]
+const LANES: usize = 8;
+
+/// Secret number generators for a batch of buyers, laid out so the compiler can advance all
+/// lanes at once with SIMD instructions
+struct SecretNumberLanes {
+    n: [u64; LANES],
+}
+
+impl SecretNumberLanes {
+    fn new(seeds: &[u64]) -> SecretNumberLanes {
+        let mut n = [0; LANES];
+        n[..seeds.len()].copy_from_slice(seeds);
+
+        SecretNumberLanes { n }
+    }
+
+    fn advance(&mut self) {
+        for n in &mut self.n {
+            *n ^= *n << 6;
+            *n &= 0xffffff;
+            *n ^= *n >> 5;
+            *n &= 0xffffff;
+            *n ^= *n << 11;
+            *n &= 0xffffff;
+        }
+    }
 }
 
 struct SecretNumbers {
@@ -102,6 +496,114 @@ impl SecretNumbers {
     fn new(n: u64) -> SecretNumbers {
         SecretNumbers { n }
     }
+
+    /// Returns the secret number `n` steps after `seed` without stepping through the ones in
+    /// between.
+    pub fn jump(seed: u64, n: u64) -> u64 {
+        if n == 0 {
+            seed
+        } else {
+            BitMatrix::step().pow(n).apply(seed & SECRET_NUMBER_MASK)
+        }
+    }
+
+    /// Returns the number of steps after which the secret numbers following `seed` repeat. Bits of
+    /// `seed` above the 24th are discarded by the first step and so don't affect the period.
+    pub fn period(seed: u64) -> u64 {
+        // The step function is invertible, so every secret number lies on a cycle whose length
+        // divides the order of the step matrix; for this generator, that order is 2^24 - 1
+        const ORDER: u64 = (1 << SECRET_NUMBER_BITS) - 1;
+
+        let seed = seed & SECRET_NUMBER_MASK;
+        let mut period = ORDER;
+
+        for factor in prime_factors(ORDER) {
+            while period.is_multiple_of(factor) && Self::jump(seed, period / factor) == seed {
+                period /= factor;
+            }
+        }
+
+        period
+    }
+}
+
+const SECRET_NUMBER_BITS: usize = 24;
+const SECRET_NUMBER_MASK: u64 = (1 << SECRET_NUMBER_BITS) - 1;
+
+/// A linear map over GF(2) on 24-bit secret numbers, stored as the images of each basis vector
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct BitMatrix {
+    columns: [u64; SECRET_NUMBER_BITS],
+}
+
+impl BitMatrix {
+    fn identity() -> BitMatrix {
+        BitMatrix {
+            columns: std::array::from_fn(|bit| 1 << bit),
+        }
+    }
+
+    /// The matrix for a single step of the secret number generator; every operation in the step
+    /// is a shift, a mask or an xor, so it's linear over GF(2).
+    fn step() -> BitMatrix {
+        BitMatrix {
+            columns: std::array::from_fn(|bit| SecretNumbers::new(1 << bit).nth(1).unwrap()),
+        }
+    }
+
+    fn apply(&self, v: u64) -> u64 {
+        self.columns
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| v & (1 << bit) != 0)
+            .fold(0, |acc, (_, column)| acc ^ column)
+    }
+
+    /// Returns the matrix that applies `other` and then `self`.
+    fn compose(&self, other: &BitMatrix) -> BitMatrix {
+        BitMatrix {
+            columns: other.columns.map(|column| self.apply(column)),
+        }
+    }
+
+    fn pow(&self, mut exponent: u64) -> BitMatrix {
+        let mut result = BitMatrix::identity();
+        let mut base = *self;
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.compose(&base);
+            }
+
+            base = base.compose(&base);
+            exponent >>= 1;
+        }
+
+        result
+    }
+}
+
+fn prime_factors(mut n: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+    let mut factor = 2;
+
+    while factor * factor <= n {
+        if n.is_multiple_of(factor) {
+            factors.push(factor);
+
+            while n.is_multiple_of(factor) {
+                n /= factor;
+            }
+        }
+
+        factor += 1;
+    }
+
+    if n > 1 {
+        factors.push(n);
+    }
+
+    factors
 }
 
 impl Iterator for SecretNumbers {
@@ -152,6 +654,115 @@ mod test {
         let monkey_market = MonkeyMarket {
             secret_numbers: vec![1, 2, 3, 2024],
         };
-        assert_eq!(23, monkey_market.max_bananas(2000));
+        for threads in [1, 3] {
+            let best_sequence = monkey_market.max_bananas(2000, 4, threads).unwrap();
+
+            assert_eq!(vec![-2, 1, -1, 3], best_sequence.sequence);
+            assert_eq!(23, best_sequence.bananas);
+            assert_eq!(vec![7, 7, 0, 9], best_sequence.bananas_by_buyer);
+        }
+    }
+
+    #[test]
+    fn test_top_sequences() {
+        let monkey_market = MonkeyMarket {
+            secret_numbers: vec![123],
+        };
+
+        let summarize = |top_sequences: Vec<SequenceBananas>| -> Vec<(Vec<i8>, u32)> {
+            top_sequences
+                .into_iter()
+                .map(|sequence_bananas| (sequence_bananas.sequence, sequence_bananas.bananas))
+                .collect()
+        };
+
+        // Prices for the first 10 secret numbers: 3, 0, 6, 5, 4, 4, 6, 4, 4, 2
+        assert_eq!(
+            vec![(vec![-1, -1, 0, 2], 6), (vec![-3, 6, -1, -1], 4)],
+            summarize(monkey_market.top_sequences(9, 4, 2, 1).unwrap())
+        );
+
+        // Ties are broken by packed sequence
+        assert_eq!(
+            vec![(vec![-3, 6], 6), (vec![0, 2], 6), (vec![6, -1], 5)],
+            summarize(monkey_market.top_sequences(9, 2, 3, 1).unwrap())
+        );
+
+        assert!(monkey_market.top_sequences(9, 0, 1, 1).is_err());
+        assert!(monkey_market.top_sequences(9, 6, 1, 1).is_err());
+
+        // Too few iterations to see a full sequence
+        let best_sequence = monkey_market.max_bananas(3, 4, 1).unwrap();
+
+        assert_eq!(0, best_sequence.bananas);
+        assert_eq!(vec![0], best_sequence.bananas_by_buyer);
+    }
+
+    #[test]
+    fn test_unpack_sequence() {
+        for sequence in [vec![-2, 1, -1, 3], vec![-9, 9, 0], vec![4]] {
+            assert_eq!(
+                sequence,
+                MonkeyMarket::unpack_sequence(
+                    MonkeyMarket::pack_sequence(&sequence),
+                    sequence.len()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_batched_matches_by_buyer() {
+        let monkey_market = MonkeyMarket {
+            secret_numbers: (1..=37).map(|i| i * 7919).collect(),
+        };
+
+        assert_eq!(
+            monkey_market.secret_number_sum_by_buyer(100),
+            monkey_market.secret_number_sum(100)
+        );
+
+        let expected = monkey_market.max_bananas_by_buyer(2000);
+
+        for threads in 1..=5 {
+            assert_eq!(
+                expected,
+                monkey_market.max_bananas(2000, 4, threads).unwrap().bananas
+            );
+        }
+    }
+
+    #[test]
+    fn test_jump() {
+        let seeds = [0, 1, 123, 2024, 0xffffff, 1 << 30 | 123];
+
+        for seed in seeds {
+            for n in [0, 1, 2, 10, 2000] {
+                assert_eq!(
+                    SecretNumbers::new(seed).nth(n).unwrap(),
+                    SecretNumbers::jump(seed, n as u64)
+                );
+            }
+        }
+
+        let monkey_market = MonkeyMarket {
+            secret_numbers: vec![1, 10, 100, 2024],
+        };
+
+        assert_eq!(37327623, monkey_market.secret_number_sum_after(2000));
+    }
+
+    #[test]
+    fn test_period() {
+        assert_eq!(1, SecretNumbers::period(0));
+        assert_eq!(16777215, SecretNumbers::period(123));
+
+        let period = SecretNumbers::period(2024);
+
+        assert_eq!(2024, SecretNumbers::jump(2024, period));
+        assert_eq!(
+            SecretNumbers::jump(2024, 1_000_000_000_000 % period),
+            SecretNumbers::jump(2024, 1_000_000_000_000)
+        );
     }
 }